@@ -5,14 +5,15 @@ pub trait CanvasAPI: Drop {
     /// Takes buffer of pixels and display it on the canvas.
     ///
     /// # Requirement 1:
-    /// buffer must consists of `width * height * 3` of unsigned bytes. 
+    /// buffer must consists of `width * height * 4` of unsigned bytes, 
+    /// in RGBA order. 
     /// It must be linear in memory.
     ///
     /// # Requirement 2:
     /// All additional operations like polling events or redrawing 
     /// of the canvas must be placed there too. In fact, all necessary 
     /// logic(excluding handling of events) must be there.
-    fn update(&mut self, buffer: *const (u8, u8, u8));
+    fn update(&mut self, buffer: *const (u8, u8, u8, u8));
 }
 
 /// # EventAPI provides abstraction under system event queue.
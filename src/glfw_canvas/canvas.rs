@@ -27,7 +27,7 @@ impl CanvasAPI for GlfwContext {
         }
     }
 
-    fn update(&mut self, buffer: *const (u8, u8, u8)) {
+    fn update(&mut self, buffer: *const (u8, u8, u8, u8)) {
         let (width, height) = self.window.get_size();
         unsafe {
             gl::TexSubImage2D(gl::TEXTURE_2D,
@@ -36,7 +36,7 @@ impl CanvasAPI for GlfwContext {
                            0,
                            width as i32,
                            height as i32,
-                           gl::RGB,
+                           gl::RGBA,
                            gl::UNSIGNED_BYTE,
                            buffer as *const c_void); 
 
@@ -81,7 +81,7 @@ fn init_opengl(window: &mut glfw::Window) {
         1, 2, 3,
     ];
     let (mut VBO, mut VAO, mut EBO, mut texture) = (0, 0, 0, 0);
-    let data = vec![(0_u8, 0_u8, 0_u8); (width * height) as usize];
+    let data = vec![(0_u8, 0_u8, 0_u8, 0_u8); (width * height) as usize];
 
     gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
     unsafe {
@@ -118,11 +118,11 @@ fn init_opengl(window: &mut glfw::Window) {
 
         gl::TexImage2D(gl::TEXTURE_2D,
                        0,
-                       gl::RGB as i32,
+                       gl::RGBA as i32,
                        width as i32,
                        height as i32,
                        0,
-                       gl::RGB,
+                       gl::RGBA,
                        gl::UNSIGNED_BYTE,
                        data.as_ptr() as *const c_void);
     }
@@ -1,12 +1,270 @@
 use nalgebra as na;
-use super::texture::Texture;
 use na::Vector4 as v4;
 use na::Vector3 as v3;
 use na::Vector2 as v2;
-use na::Matrix4 as mat4;
+use crate::rasterizer_2::GSample;
 
-trait GeometryVertex {
-    type InputVertex;
-    type Vertex;
+/// Where a point lies between vertices, both perspective-correctly and
+/// linearly in window space.
+#[derive(Clone, Copy, Debug)]
+pub struct Weights<W> {
+    pub perspective : W,
+    pub linear      : W,
 }
 
+/// Values a vertex shader hands over to the fragment shader. They get
+/// blended when primitives are clipped and interpolated across primitives,
+/// perspective-correctly unless wrapped in `Flat` or `NoPerspective`.
+///
+/// Tuples and arrays of varyings are varyings too, so every attribute can
+/// have its own qualifier.
+pub trait Varyings: Copy + Send + Sync + 'static {
+    /// Value `t` of the way from `self` to `other`.
+    fn lerp(&self, other: &Self, t: Weights<f32>) -> Self;
+
+    /// Sum of the values at the corners of a triangle, weighted by
+    /// `weights`. The weights add up to one.
+    fn interpolate(corners: [&Self; 3], weights: Weights<v3<f32>>) -> Self;
+
+    /// `self - other`, used for derivatives.
+    fn difference(&self, other: &Self) -> Self;
+
+    /// Replaces the `Flat` values with the ones of the provoking vertex.
+    fn flatten(&mut self, _provoking: &Self) {}
+}
+
+/// Change of the varyings from one pixel to the next one towards greater
+/// window x and y.
+#[derive(Clone, Copy, Debug)]
+pub struct Derivatives<V> {
+    pub dx          : V,
+    pub dy          : V,
+}
+
+/// Keeps the value of the provoking vertex, the first one of a primitive,
+/// over the whole primitive.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Flat<T>(pub T);
+
+/// Interpolates linearly in window space, ignoring perspective.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NoPerspective<T>(pub T);
+
+impl<T: Varyings> Varyings for Flat<T> {
+    fn lerp(&self, _: &Self, _: Weights<f32>) -> Self {
+        *self
+    }
+
+    fn interpolate(corners: [&Self; 3], _: Weights<v3<f32>>) -> Self {
+        *corners[0]
+    }
+
+    fn difference(&self, other: &Self) -> Self {
+        Flat(self.0.difference(&other.0))
+    }
+
+    fn flatten(&mut self, provoking: &Self) {
+        *self = *provoking;
+    }
+}
+
+/// Integers can't be interpolated, so they are only varyings when flat.
+macro_rules! impl_flat_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Varyings for Flat<$t> {
+                fn lerp(&self, _: &Self, _: Weights<f32>) -> Self {
+                    *self
+                }
+
+                fn interpolate(corners: [&Self; 3], _: Weights<v3<f32>>) -> Self {
+                    *corners[0]
+                }
+
+                fn difference(&self, other: &Self) -> Self {
+                    Flat(self.0.wrapping_sub(other.0))
+                }
+
+                fn flatten(&mut self, provoking: &Self) {
+                    *self = *provoking;
+                }
+            }
+        )*
+    };
+}
+
+impl_flat_integer!(u32, i32);
+
+impl<T: Varyings> Varyings for NoPerspective<T> {
+    fn lerp(&self, other: &Self, t: Weights<f32>) -> Self {
+        NoPerspective(self.0.lerp(&other.0, Weights { perspective: t.linear, linear: t.linear }))
+    }
+
+    fn interpolate(corners: [&Self; 3], weights: Weights<v3<f32>>) -> Self {
+        let weights = Weights { perspective: weights.linear, linear: weights.linear };
+        NoPerspective(T::interpolate([&corners[0].0, &corners[1].0, &corners[2].0], weights))
+    }
+
+    fn difference(&self, other: &Self) -> Self {
+        NoPerspective(self.0.difference(&other.0))
+    }
+
+    fn flatten(&mut self, provoking: &Self) {
+        self.0.flatten(&provoking.0);
+    }
+}
+
+impl Varyings for () {
+    fn lerp(&self, _: &Self, _: Weights<f32>) -> Self {}
+
+    fn interpolate(_: [&Self; 3], _: Weights<v3<f32>>) -> Self {}
+
+    fn difference(&self, _: &Self) -> Self {}
+}
+
+macro_rules! impl_varyings {
+    ($($t:ty),*) => {
+        $(
+            impl Varyings for $t {
+                fn lerp(&self, other: &Self, t: Weights<f32>) -> Self {
+                    *self + (*other - *self) * t.perspective
+                }
+
+                fn interpolate(corners: [&Self; 3], weights: Weights<v3<f32>>) -> Self {
+                    let w = weights.perspective;
+                    *corners[0] * w.x + *corners[1] * w.y + *corners[2] * w.z
+                }
+
+                fn difference(&self, other: &Self) -> Self {
+                    *self - *other
+                }
+            }
+        )*
+    };
+}
+
+impl_varyings!(f32, v2<f32>, v3<f32>, v4<f32>);
+
+macro_rules! impl_varyings_tuple {
+    ($(($($t:ident $i:tt),*)),*) => {
+        $(
+            impl<$($t: Varyings),*> Varyings for ($($t,)*) {
+                fn lerp(&self, other: &Self, t: Weights<f32>) -> Self {
+                    ($(self.$i.lerp(&other.$i, t),)*)
+                }
+
+                fn interpolate(corners: [&Self; 3], weights: Weights<v3<f32>>) -> Self {
+                    ($($t::interpolate([&corners[0].$i, &corners[1].$i, &corners[2].$i], weights),)*)
+                }
+
+                fn difference(&self, other: &Self) -> Self {
+                    ($(self.$i.difference(&other.$i),)*)
+                }
+
+                fn flatten(&mut self, provoking: &Self) {
+                    $(self.$i.flatten(&provoking.$i);)*
+                }
+            }
+        )*
+    };
+}
+
+impl_varyings_tuple!(
+    (A 0),
+    (A 0, B 1),
+    (A 0, B 1, C 2),
+    (A 0, B 1, C 2, D 3),
+    (A 0, B 1, C 2, D 3, E 4),
+    (A 0, B 1, C 2, D 3, E 4, F 5),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7));
+
+macro_rules! impl_varyings_array {
+    ($($n:expr),*) => {
+        $(
+            impl<T: Varyings> Varyings for [T; $n] {
+                fn lerp(&self, other: &Self, t: Weights<f32>) -> Self {
+                    let mut out = *self;
+                    for (out, (a, b)) in out.iter_mut().zip(self.iter().zip(other.iter())) {
+                        *out = a.lerp(b, t);
+                    }
+                    out
+                }
+
+                fn interpolate(corners: [&Self; 3], weights: Weights<v3<f32>>) -> Self {
+                    let mut out = *corners[0];
+                    for (i, out) in out.iter_mut().enumerate() {
+                        *out = T::interpolate([&corners[0][i], &corners[1][i], &corners[2][i]], weights);
+                    }
+                    out
+                }
+
+                fn difference(&self, other: &Self) -> Self {
+                    let mut out = *self;
+                    for (out, (a, b)) in out.iter_mut().zip(self.iter().zip(other.iter())) {
+                        *out = a.difference(b);
+                    }
+                    out
+                }
+
+                fn flatten(&mut self, provoking: &Self) {
+                    for (value, provoking) in self.iter_mut().zip(provoking.iter()) {
+                        value.flatten(provoking);
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_varyings_array!(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16);
+
+/// Runs once for every vertex of a draw. Uniforms are the fields of the
+/// shader: every vertex of the draw sees the same ones.
+pub trait VertexShader {
+    type Input;
+    type Varyings: Varyings;
+
+    /// Returns the clip-space position of the vertex and its varyings.
+    fn shade(&self, input: &Self::Input) -> (v4<f32>, Self::Varyings);
+}
+
+/// Vertex shader of instanced draws: runs once for every vertex of every
+/// instance, and sees the data of the instance besides the vertex.
+pub trait InstancedVertexShader {
+    type Input;
+    type Instance;
+    type Varyings: Varyings;
+
+    /// Returns the clip-space position of the vertex and its varyings.
+    fn shade(&self, input: &Self::Input, instance: &Self::Instance) -> (v4<f32>, Self::Varyings);
+}
+
+/// Runs once for every pixel covered by a primitive, possibly on several
+/// threads at once. Uniforms are the fields of the shader.
+///
+/// The output is a color by default. A shader outputting a `GSample` writes
+/// every render target of the G-buffer at once, see `LightingShader`.
+pub trait FragmentShader<Output = (u8, u8, u8, u8)>: Sync {
+    type Varyings: Varyings;
+
+    /// Returns the output of the fragment, or `None` to discard it.
+    /// Discarded fragments leave every buffer untouched.
+    ///
+    /// Triangles are shaded in 2×2 quads of pixels, and `derivatives` are
+    /// the differences between neighbours within the quad. Lines take them
+    /// along the line, and points have none.
+    fn shade(
+        &self,
+        varyings    : &Self::Varyings,
+        derivatives : &Derivatives<Self::Varyings>) -> Option<Output>;
+}
+
+/// Second pass of deferred shading: runs once for every sample of the
+/// G-buffer a geometry pass wrote to, possibly on several threads at once,
+/// and turns it into a color. Lights are uniforms, so their cost doesn't
+/// depend on how much geometry was drawn.
+pub trait LightingShader: Sync {
+    /// Returns the RGBA color of the sample, or `None` to leave it as is.
+    fn shade(&self, sample: &GSample) -> Option<(u8, u8, u8, u8)>;
+}
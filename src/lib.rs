@@ -0,0 +1,5 @@
+extern crate nalgebra as na;
+
+pub mod julia;
+pub mod rasterizer_2;
+pub mod texture;
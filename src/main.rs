@@ -1,8 +1,5 @@
 mod gcanvas;
 mod glfw_canvas;
-mod rasterizer_2;
-mod texture;
-use std::time::{Duration, Instant};
 use std::collections::HashSet;
 
 extern crate nalgebra as na;
@@ -10,6 +7,7 @@ extern crate bmp;
 
 use gcanvas::{CanvasAPI, EventAPI, Event};
 use glfw_canvas::GlfwContext;
+use rust_test::{rasterizer_2, texture};
 use na::Vector3 as v3;
 use na::Vector2 as v2;
 use na::Matrix4 as mat4;
@@ -48,7 +46,7 @@ mod camera {
             mat4::new(
                 cam_right.x,    cam_right.y,    cam_right.z,    0.,
                 cam_up.x,       cam_up.y,       cam_up.z,       0.,
-                direction.x,    direction.y,    direction.z,    0.,
+                -direction.x,   -direction.y,   -direction.z,   0.,
                 0.,             0.,             0.,             1.,
             ) * mat4::new(
                 1., 0., 0., -self.position.x,
@@ -76,78 +74,90 @@ mod camera {
 fn main() {
     let mut ctx = GlfwContext::new(512, 512, "Julia3D");
     let mut julia = rasterizer_2::Julia3D::new(512, 512);
+    julia.set_cull_mode(rasterizer_2::CullMode::Back);
+    julia.set_sample_pattern(rasterizer_2::SamplePattern::standard(4).unwrap());
+    // `JULIA_STATS=csv` or `JULIA_STATS=json` prints the stats of every frame.
+    let stats_format = std::env::var("JULIA_STATS").ok();
+    julia.set_profiling(stats_format.is_some());
+    if stats_format.as_deref() == Some("csv") {
+        println!("{}", rasterizer_2::FrameStats::csv_header());
+    }
     let tex = texture::Texture::new(std::path::Path::new("./textures/tex1.bmp"));
+    // The top row of the bitmap is at tex coord `y = 1`.
     let cube_vertices = [
         // Front vertices
         rasterizer_2::IVertex {
             coords      : v3::new(1., 1., -1.),
-            tex_coords  : v2::new(1., 1.),
+            tex_coords  : v2::new(1., -1.),
         }, 
         rasterizer_2::IVertex {
             coords      : v3::new(1., -1., -1.),
-            tex_coords  : v2::new(1., -1.),
+            tex_coords  : v2::new(1., 1.),
         },
         rasterizer_2::IVertex {
             coords      : v3::new(-1., -1., -1.),
-            tex_coords  : v2::new(-1., -1.),
+            tex_coords  : v2::new(-1., 1.),
         },
         rasterizer_2::IVertex {
             coords      : v3::new(-1., 1., -1.),
-            tex_coords  : v2::new(-1., 1.),
+            tex_coords  : v2::new(-1., -1.),
         },
 
         // Back vertices
         rasterizer_2::IVertex {
             coords      : v3::new(1., 1., 1.),
-            tex_coords  : v2::new(-1., -1.),
+            tex_coords  : v2::new(-1., 1.),
         }, 
         rasterizer_2::IVertex {
             coords      : v3::new(1., -1., 1.),
-            tex_coords  : v2::new(-1., 1.),
+            tex_coords  : v2::new(-1., -1.),
         },
         rasterizer_2::IVertex {
             coords      : v3::new(-1., -1., 1.),
-            tex_coords  : v2::new(1., 1.),
+            tex_coords  : v2::new(1., -1.),
         },
         rasterizer_2::IVertex {
             coords      : v3::new(-1., 1., 1.),
-            tex_coords  : v2::new(1., -1.),
+            tex_coords  : v2::new(1., 1.),
         }
     ];
 
-    let cube_faces = [
+    // Faces are wound counter-clockwise when looked at from outside the cube.
+    let cube_faces: [(u16, u16, u16); 12] = [
         // front
         (0, 1, 2),
         (0, 2, 3),
     
         // back
-        (4, 5, 6),
+        (4, 6, 5),
         (4, 7, 6),
 
         // left 
-        (2, 3, 7),
+        (2, 7, 3),
         (2, 6, 7),
 
         // right 
         (1, 0, 4),
-        (1, 5, 4),
+        (1, 4, 5),
 
         // top
-        (3, 0, 4),
+        (3, 4, 0),
         (3, 7, 4),
 
         // bot
         (2, 1, 5),
-        (2, 6, 5),
+        (2, 5, 6),
     ];
 
+    let cube = rasterizer_2::Mesh::new(&cube_vertices, &cube_faces);
+
     let mut t: f32 = 0.;
     let dt = 0.005;
     let mut working = true;
 
     let mut camera = camera::ResponsiveCamera::new(
         v3::new(0., 0., -5.),
-        v3::new(0., 0., -10.));
+        v3::new(0., 0., 0.));
 
     let mut mouse_pos: Option<v2<f32>> = None;
     let mouse_sensativity = 0.01;
@@ -172,26 +182,36 @@ fn main() {
         }
 
         t += dt;
-        julia.render(
-            &cube_vertices, 
-            &cube_faces,
-            &tex,
-            camera.homogeneous() * get_model(t, 0., 0., 5.));
-
-        julia.render(
-            &cube_vertices, 
-            &cube_faces,
-            &tex,
-            camera.homogeneous() * get_model(4. * t + 5., 3., 0., 5.));
-
-        julia.render(
-            &cube_vertices, 
-            &cube_faces,
-            &tex,
-            camera.homogeneous() * get_model(6. * t + 5., -3., 0., 2.));
+        let instance = |model| rasterizer_2::Instance {
+            model,
+            tint        : (255, 255, 255, 255),
+            texture     : 0,
+        };
+        let instances = [
+            instance(get_model(t, 0., 0., 5.)),
+            instance(get_model(4. * t + 5., 3., 0., 5.)),
+            instance(get_model(6. * t + 5., -3., 0., 2.)),
+        ];
+        let result = julia.render_mesh_instanced(
+            &cube,
+            &[&tex],
+            camera.homogeneous(),
+            &instances);
+        if let Err(err) = result {
+            eprintln!("Failed to draw the cubes: {}", err);
+        }
 
+        julia.resolve();
         ctx.update(julia.buff_ptr());
 
+        let stats = julia.frame_stats();
+        julia.reset_frame_stats();
+        match stats_format.as_deref() {
+            Some("csv")     => println!("{}", stats.to_csv()),
+            Some("json")    => println!("{}", stats.to_json()),
+            _               => {},
+        }
+
         for event in ctx.events() {
             match event {
                 Event::Close => {
@@ -1,11 +1,50 @@
 use nalgebra as na;
+use super::julia::{Derivatives, FragmentShader, InstancedVertexShader, LightingShader, Varyings, VertexShader, Weights};
 use super::texture::Texture;
 use na::Vector4 as v4;
 use na::Vector3 as v3;
 use na::Vector2 as v2;
 use na::Matrix4 as mat4;
 
-use std::time::{Instant};
+use rayon::prelude::*;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+mod blend;
+mod bounds;
+mod clip;
+mod deferred;
+mod error;
+mod framebuffer;
+mod hiz;
+mod index;
+mod multisample;
+mod projection;
+mod state;
+mod stats;
+mod textured;
+mod tiles;
+mod topology;
+mod viewport;
+
+pub use blend::{BlendFactor, BlendOp, BlendState};
+pub use bounds::{BoundingVolume, CullCounters, Mesh};
+pub use deferred::{GSample, PointLight, PointLightShader};
+pub use error::RenderError;
+pub use framebuffer::{Framebuffer, RenderTarget};
+pub use index::Index;
+pub use multisample::SamplePattern;
+pub use projection::Projection;
+pub use state::{CompareFunc, CullMode, DepthBias, DepthState, FrontFace, PolygonMode, StencilOp, StencilState};
+pub use stats::FrameStats;
+pub use textured::{Instance, InstancedShader, TexturedShader};
+pub use topology::PrimitiveTopology;
+pub use viewport::{Viewport, Origin};
+use hiz::{DepthRange, HiZTile, BLOCK_SIZE};
+use multisample::MAX_SAMPLES;
+use stats::Stopwatch;
+use tiles::{Bins, TileBuffers, TileRows, TILE_SIZE};
 
 #[derive(Clone, Copy)]
 pub struct IVertex {
@@ -14,280 +53,1779 @@ pub struct IVertex {
 }
 
 #[derive(Clone, Copy)]
-struct Vertex {
+struct Vertex<V> {
     coords      : v4<f32>,
-    tex_coords  : v2<f32>,
+    varyings    : V,
+}
+
+impl<V: Varyings> Vertex<V> {
+    /// Blends two clip-space vertices, `t` of the way from `self` to `other`.
+    fn lerp(&self, other: &Vertex<V>, t: f32) -> Vertex<V> {
+        let coords = self.coords + (other.coords - self.coords) * t;
+        // How far along the blended vertex ends up once both are projected.
+        let linear = if coords.w != 0. { t * other.coords.w / coords.w } else { t };
+        Vertex {
+            coords,
+            varyings    : self.varyings.lerp(&other.varyings, Weights { perspective: t, linear }),
+        }
+    }
+}
+
+/// Triangle that went through clipping and culling, mapped onto the window.
+struct Triangle<V> {
+    /// Vertices in normalized device coordinates, with the clip-space `w`
+    /// kept in `coords.w`.
+    vertices    : [Vertex<V>; 3],
+    /// Window coordinates of the vertices in counter-clockwise order, snapped
+    /// to the subpixel grid.
+    dots        : [v2<i64>; 3],
+    /// Index in `vertices` of every dot.
+    order       : [usize; 3],
+    /// Whether the edge from `vertices[k]` to the next vertex is part of the
+    /// outline of the clipped polygon rather than a diagonal of its fan.
+    outline     : [bool; 3],
+    /// Added to the edge function opposite to every dot, so that only pixels
+    /// on top and left edges pass the `>= 0` test.
+    bias        : [i64; 3],
+    /// Doubled area of the triangle in squared subpixels.
+    area        : i64,
+    /// Change of depth from one pixel to the next along each axis.
+    depth_slope : v2<f32>,
+    /// Inclusive range of pixels the triangle may cover.
+    min         : v2<i32>,
+    max         : v2<i32>,
+}
+
+impl<V> Triangle<V> {
+    /// Window coordinates of `vertices[k]`.
+    fn dot(&self, k: usize) -> v2<i64> {
+        self.dots[self.order.iter().position(|o| *o == k).unwrap()]
+    }
+
+    /// Range of the depths of the samples the triangle covers.
+    fn depth_range(&self) -> DepthRange {
+        let mut range = DepthRange::new(self.vertices[0].coords.z);
+        range.include(self.vertices[1].coords.z);
+        range.include(self.vertices[2].coords.z);
+        range
+    }
+
+    /// How far behind the depth buffer the outline still passes in
+    /// hidden-line mode: the change of depth across a pixel, plus some room
+    /// for rounding, as the depth of faces parallel to the screen doesn't
+    /// change at all.
+    fn outline_tolerance(&self) -> f32 {
+        let depths = self.depth_range();
+        self.depth_slope.abs().sum() + (depths.min.abs().max(depths.max.abs()) + 1.) * 1e-5
+    }
+}
+
+/// Line that went through clipping, mapped onto the window.
+struct Line<V> {
+    vertices    : [Vertex<V>; 2],
+    dots        : [v2<i64>; 2],
+    min         : v2<i32>,
+    max         : v2<i32>,
+}
+
+/// Point inside the view frustum, mapped onto the window.
+struct Point<V> {
+    vertex      : Vertex<V>,
+    /// Fixed-point window coordinates of the bottom-left and the top-right
+    /// corners of the square.
+    from        : v2<i64>,
+    to          : v2<i64>,
+    min         : v2<i32>,
+    max         : v2<i32>,
+}
+
+enum Primitive<V> {
+    Triangle(Triangle<V>),
+    Line(Line<V>),
+    Point(Point<V>),
 }
 
-type Basis<'a> = [&'a Vertex; 3];
+impl<V> Primitive<V> {
+    /// Inclusive range of pixels the primitive may cover.
+    fn bounds(&self) -> (v2<i32>, v2<i32>) {
+        match self {
+            Primitive::Triangle(triangle)   => (triangle.min, triangle.max),
+            Primitive::Line(line)           => (line.min, line.max),
+            Primitive::Point(point)         => (point.min, point.max),
+        }
+    }
+}
 
 #[derive(Clone, Copy)]
-struct Raster<'a> {
+struct Raster {
     window_coords   : v2<i32>,
+    /// Barycentric coordinates of the pixel center.
     baricentric     : v3<f32>,
-    basis           : &'a Basis<'a>,
+    /// Bit `i` is set when sample `i` of the pixel is inside the triangle.
+    coverage        : u32,
 }
 
-struct Fragment {
+/// Pixel of a line drawn along the edge between two vertices of a primitive.
+#[derive(Clone, Copy)]
+struct LinePixel {
     window_coords   : v2<i32>,
+    edge            : (usize, usize),
+    /// How far along the edge the pixel center is.
+    t               : f32,
+    /// How much `t` changes from one pixel to the next.
+    dt              : v2<f32>,
+    /// How far behind the depth buffer the fragment still passes.
+    tolerance       : f32,
+}
+
+struct Fragment<V> {
+    window_coords   : v2<i32>,
+    /// Depth at the pixel center.
     depth           : f32,
-    tex_coords      : v2<f32>,
+    varyings        : V,
+    coverage        : u32,
+}
+
+/// Buffers reused from draw to draw by the draws sharing a type of varyings.
+struct Scratch<V> {
+    vertices        : Vec<Vertex<V>>,
+    primitives      : Vec<Primitive<V>>,
+    /// Stats of the current draw.
+    stats           : FrameStats,
+}
+
+/// Threads shading the tiles.
+enum Threads {
+    /// Rayon's global pool, shared between renderers.
+    Global,
+    /// The thread drawing, without spawning any.
+    Calling,
+    Pool(rayon::ThreadPool),
+}
+
+impl Threads {
+    fn count(&self) -> usize {
+        match self {
+            Threads::Global     => rayon::current_num_threads(),
+            Threads::Calling    => 1,
+            Threads::Pool(pool) => pool.current_num_threads(),
+        }
+    }
+
+    /// Runs `op` so that what it spawns on rayon goes to these threads.
+    fn install<R: Send, OP: FnOnce() -> R + Send>(&self, op: OP) -> R {
+        match self {
+            Threads::Pool(pool) => pool.install(op),
+            _                   => op(),
+        }
+    }
+}
+
+/// Screen tile together with the parts of the buffers it owns.
+struct Tile<'a, C> {
+    /// Window coordinates of the bottom-left pixel.
+    origin          : v2<i32>,
+    samples         : usize,
+    depth_state     : DepthState,
+    stencil_state   : StencilState,
+    blend_state     : BlendState,
+    color_rows      : TileRows<'a, C>,
+    depth_rows      : TileRows<'a, f32>,
+    stencil_rows    : TileRows<'a, u8>,
+    hiz             : HiZTile<'a>,
+    /// Whether the stages get timed.
+    profiling       : bool,
+    stats           : FrameStats,
+}
+
+impl<'a, C: RenderTarget> Tile<'a, C> {
+    /// Row of the tile and index in that row of the first sample of the
+    /// pixel. The samples of a pixel are stored next to each other.
+    fn offset(&self, window_coords: v2<i32>) -> (usize, usize) {
+        let local = window_coords - self.origin;
+        debug_assert!(0 <= local.x && local.x < TILE_SIZE && 0 <= local.y && local.y < TILE_SIZE);
+        (local.y as usize, local.x as usize * self.samples)
+    }
+
+    /// Runs the stencil and the depth tests on the sample, updates its
+    /// stencil value and stores its depth if both pass and depth writes are
+    /// enabled.
+    fn depth_stencil_test(&mut self, row: usize, sample: usize, depth: f32, tolerance: f32) -> bool {
+        let stencil = self.stencil_state;
+        if stencil.enabled {
+            let stored = self.stencil_rows[row][sample];
+            if !stencil.compare.compare(stencil.reference & stencil.read_mask, stored & stencil.read_mask) {
+                self.update_stencil(row, sample, stencil.fail_op);
+                return false;
+            }
+        }
+        let pass = self.depth_state.compare.test(depth, self.depth_rows[row][sample], tolerance);
+        if stencil.enabled {
+            self.update_stencil(row, sample, if pass { stencil.pass_op } else { stencil.depth_fail_op });
+        }
+        if pass && self.depth_state.write_enabled {
+            self.depth_rows[row][sample] = depth;
+        }
+        self.stats.samples_passed += pass as u64;
+        pass
+    }
+
+    /// Whether samples with depths in `depths` all fail the depth test
+    /// against `stored`, and skipping them leaves the stencil buffer as is.
+    fn rejects(&self, depths: DepthRange, stored: DepthRange) -> bool {
+        self.stencil_state.keeps_failed() && self.depth_state.compare.rejects(depths, stored)
+    }
+
+    /// Blends the color, or whatever the fragment shader outputs, into the
+    /// sample.
+    fn write_color(&mut self, row: usize, sample: usize, color: C) {
+        let dst = &mut self.color_rows[row][sample];
+        *dst = color.blend(*dst, &self.blend_state);
+    }
+
+    fn update_stencil(&mut self, row: usize, sample: usize, op: StencilOp) {
+        let mask = self.stencil_state.write_mask;
+        let stored = self.stencil_rows[row][sample];
+        let value = op.apply(stored, self.stencil_state.reference);
+        self.stencil_rows[row][sample] = (stored & !mask) | (value & mask);
+    }
 }
 
 pub struct Julia3D {
-    color_buffer    : Vec<(u8, u8, u8)>,
-    depth_buffer    : Vec<f32>,
-    shape           : v2<i32>,
+    /// Where draws go, the window unless inside `render_to`.
+    framebuffer     : Framebuffer,
+    projection      : Projection,
+    viewport        : Viewport,
+    cull_mode       : CullMode,
+    front_face      : FrontFace,
+    polygon_mode    : PolygonMode,
+    depth_state     : DepthState,
+    stencil_state   : StencilState,
+    blend_state     : BlendState,
+    bins            : Bins,
+    threads         : Threads,
+    /// `Scratch` of every type of varyings drawn so far.
+    scratch         : HashMap<TypeId, Box<dyn Any>>,
+    cull_counters   : CullCounters,
+    frame_stats     : FrameStats,
+    profiling       : bool,
+    /// Samples that passed the depth and stencil tests since the renderer
+    /// was created, for occlusion queries.
+    samples_passed  : u64,
 }
 
 impl Julia3D {
     pub fn new(width: i32, height: i32) -> Self {
-        let buff_sz = (width * height) as usize;
+        let viewport = Viewport::new(0, 0, width, height);
         Self {
-            color_buffer    : vec![(0_u8, 0_u8, 0_u8); buff_sz],
-            depth_buffer    : vec![-1000.; buff_sz],
-            shape           : v2::new(width, height),
+            framebuffer     : Framebuffer::new(width, height),
+            projection      : Projection::perspective(
+                std::f32::consts::FRAC_PI_2,
+                viewport.aspect(),
+                0.01,
+                1000.),
+            viewport,
+            cull_mode       : CullMode::None,
+            front_face      : FrontFace::Ccw,
+            polygon_mode    : PolygonMode::Fill,
+            depth_state     : DepthState::default(),
+            stencil_state   : StencilState::default(),
+            blend_state     : BlendState::default(),
+            bins            : Bins::new(v2::new(width, height)),
+            threads         : Threads::Global,
+            scratch         : HashMap::new(),
+            cull_counters   : CullCounters::default(),
+            frame_stats     : FrameStats::default(),
+            profiling       : false,
+            samples_passed  : 0,
         }
     }
 
-    pub fn buff_ptr(&self) -> *const (u8, u8, u8) { self.color_buffer.as_ptr() }
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    /// Sets the rectangle the following draws are mapped onto. Pixels of the
+    /// viewport outside of the framebuffer are never touched.
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+    }
+
+    pub fn set_cull_mode(&mut self, cull_mode: CullMode) {
+        self.cull_mode = cull_mode;
+    }
+
+    pub fn set_front_face(&mut self, front_face: FrontFace) {
+        self.front_face = front_face;
+    }
+
+    pub fn set_polygon_mode(&mut self, polygon_mode: PolygonMode) {
+        self.polygon_mode = polygon_mode;
+    }
+
+    /// A new clear depth only shows up in the buffer after the next `clear`.
+    pub fn set_depth_state(&mut self, depth_state: DepthState) {
+        self.depth_state = depth_state;
+    }
+
+    /// A new clear value only shows up in the buffer after the next `clear`.
+    pub fn set_stencil_state(&mut self, stencil_state: StencilState) {
+        self.stencil_state = stencil_state;
+    }
+
+    pub fn set_blend_state(&mut self, blend_state: BlendState) {
+        self.blend_state = blend_state;
+    }
+
+    /// Sets the number of threads shading the tiles. `0` shares rayon's
+    /// global pool between renderers, which is the default, and `1` shades
+    /// everything on the calling thread. The image doesn't depend on it.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = match threads {
+            0 => Threads::Global,
+            1 => Threads::Calling,
+            _ => Threads::Pool(rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("Can't spawn the rasterization threads")),
+        };
+    }
+
+    /// Sets where pixels get sampled. With more than one sample per pixel,
+    /// coverage and depth are tested for every sample while shading still
+    /// happens once per pixel, and `resolve` has to be called to get the
+    /// final image. The buffers of the current framebuffer are reallocated
+    /// and cleared.
+    pub fn set_sample_pattern(&mut self, sample_pattern: SamplePattern) {
+        self.framebuffer.set_sample_pattern(
+            sample_pattern,
+            self.depth_state.clear_depth,
+            self.stencil_state.clear_stencil);
+    }
+
+    /// Averages the samples of every pixel of the current framebuffer into
+    /// its color attachment. Does nothing with a single sample per pixel.
+    pub fn resolve(&mut self) {
+        self.framebuffer.resolve();
+    }
+
+    pub fn buff_ptr(&self) -> *const (u8, u8, u8, u8) {
+        self.framebuffer.color().pixels().as_ptr()
+    }
+
+    /// The framebuffer draws currently go to.
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    /// Makes the clears, draws and resolves of `f` go to `framebuffer` rather
+    /// than to the window, then switches back. The viewport is kept, so it
+    /// usually has to be set to the size of `framebuffer` too.
+    pub fn render_to<R, F: FnOnce(&mut Self) -> R>(&mut self, framebuffer: &mut Framebuffer, f: F) -> R {
+        std::mem::swap(&mut self.framebuffer, framebuffer);
+        let result = f(self);
+        std::mem::swap(&mut self.framebuffer, framebuffer);
+        result
+    }
 
     pub fn clear(&mut self) {
-        self.color_buffer = vec![(0_u8, 0_u8, 0_u8); self.color_buffer.len()];
-        self.depth_buffer = vec![-1000.0; self.depth_buffer.len()];
+        self.framebuffer.clear(self.depth_state.clear_depth, self.stencil_state.clear_stencil);
+    }
+
+    /// Attaches a G-buffer to the current framebuffer for deferred shading,
+    /// or frees it.
+    pub fn set_gbuffer(&mut self, enabled: bool) {
+        self.framebuffer.set_gbuffer(enabled);
     }
 
-    pub fn render(
+    /// Lighting pass of deferred shading: blends the color `shader` computes
+    /// for every sample of the G-buffer that a geometry pass wrote to into
+    /// the framebuffer. The G-buffer is left as is, so it can be lit again.
+    pub fn light<LS: LightingShader>(&mut self, shader: &LS) -> Result<(), RenderError> {
+        let blend_state = self.blend_state;
+        let (color_buffer, gbuffer) = match self.framebuffer.lighting_buffers_mut() {
+            Some(buffers)   => buffers,
+            None            => return Err(RenderError::MissingGBuffer),
+        };
+        let light_sample = |(color, sample): (&mut (u8, u8, u8, u8), &GSample)| {
+            if sample.material == 0 {
+                return;
+            }
+            if let Some(lit) = shader.shade(sample) {
+                *color = blend_state.blend(lit, *color);
+            }
+        };
+        if self.threads.count() > 1 {
+            self.threads.install(|| {
+                color_buffer.par_iter_mut()
+                    .zip(gbuffer.par_iter())
+                    .for_each(light_sample)
+            });
+        } else {
+            color_buffer.iter_mut()
+                .zip(gbuffer.iter())
+                .for_each(light_sample);
+        }
+        Ok(())
+    }
+
+    /// Draws a list of textured triangles, see `draw`.
+    pub fn render<I: Index>(
         &mut self,
         ivertices   : &[IVertex],
-        faces       : &[(i32, i32, i32)],
+        faces       : &[(I, I, I)],
         texture     : &Texture,
-        model_mat   : mat4<f32>) {
-        // let mut time_acc_ms = 0;
-        let vertices = self.geometry_2(ivertices, model_mat);
-        for (a, b, c) in faces {
+        model_mat   : mat4<f32>) -> Result<(), RenderError> {
+        Self::check_faces(faces, ivertices.len())?;
+        Self::validate(ivertices, model_mat)?;
+        let shader = TexturedShader {
+            mvp         : self.projection.matrix() * model_mat,
+            texture,
+        };
+        self.execute(&shader, |julia, scratch| {
+            julia.shade_vertices(scratch, ivertices, |iv| VertexShader::shade(&shader, iv))?;
+            julia.assemble_faces(scratch, faces);
+            Ok(())
+        })
+    }
+
+    /// Draws a copy of a list of textured triangles for every instance, each
+    /// with the model matrix, the tint and the texture of its instance. The
+    /// copies are drawn at once, see `draw_instanced`.
+    pub fn render_instanced<I: Index>(
+        &mut self,
+        ivertices   : &[IVertex],
+        faces       : &[(I, I, I)],
+        textures    : &[&Texture],
+        view_mat    : mat4<f32>,
+        instances   : &[Instance]) -> Result<(), RenderError> {
+        self.render_instances(ivertices, faces, None, textures, view_mat, instances)
+    }
+
+    /// Draws the mesh like `render`, unless its bounding volume is outside
+    /// of the view frustum or occluded, see `cull`. Skipped meshes aren't
+    /// checked for errors and show up in the `cull_counters`.
+    pub fn render_mesh<I: Index>(
+        &mut self,
+        mesh        : &Mesh<I>,
+        texture     : &Texture,
+        model_mat   : mat4<f32>) -> Result<(), RenderError> {
+        if self.cull(&mesh.bounds, self.projection.matrix() * model_mat) {
+            return Ok(());
+        }
+        self.render(mesh.vertices, mesh.faces, texture, model_mat)
+    }
+
+    /// Draws the mesh like `render_instanced`, skipping the instances whose
+    /// bounding volume is outside of the view frustum or occluded.
+    pub fn render_mesh_instanced<I: Index>(
+        &mut self,
+        mesh        : &Mesh<I>,
+        textures    : &[&Texture],
+        view_mat    : mat4<f32>,
+        instances   : &[Instance]) -> Result<(), RenderError> {
+        self.render_instances(mesh.vertices, mesh.faces, Some(&mesh.bounds), textures, view_mat, instances)
+    }
+
+    fn render_instances<I: Index>(
+        &mut self,
+        ivertices   : &[IVertex],
+        faces       : &[(I, I, I)],
+        bounds      : Option<&BoundingVolume>,
+        textures    : &[&Texture],
+        view_mat    : mat4<f32>,
+        instances   : &[Instance]) -> Result<(), RenderError> {
+        Self::check_faces(faces, ivertices.len())?;
+        Self::validate(ivertices, view_mat)?;
+        for (index, instance) in instances.iter().enumerate() {
+            if !instance.model.iter().all(|c| c.is_finite()) {
+                return Err(RenderError::NonFiniteTransform);
+            }
+            if instance.texture >= textures.len() {
+                return Err(RenderError::TextureIndexOutOfRange {
+                    instance        : index,
+                    texture         : instance.texture,
+                    texture_count   : textures.len(),
+                });
+            }
+        }
+        let shader = InstancedShader {
+            view_projection : self.projection.matrix() * view_mat,
+            textures,
+        };
+        let (mut culled, mut occluded) = (0, 0);
+        let result = self.execute(&shader, |julia, scratch| {
+            for instance in instances {
+                if let Some(bounds) = bounds {
+                    let mvp = shader.view_projection * instance.model;
+                    if bounds.is_outside(&mvp) {
+                        culled += 1;
+                        continue;
+                    }
+                    if julia.is_occluded(bounds, mvp) {
+                        occluded += 1;
+                        continue;
+                    }
+                }
+                julia.shade_vertices(scratch, ivertices, |iv| InstancedVertexShader::shade(&shader, iv, instance))?;
+                julia.assemble_faces(scratch, faces);
+            }
+            Ok(())
+        });
+        if bounds.is_some() {
+            self.cull_counters.tested += instances.len() as u64;
+            self.cull_counters.culled += culled;
+            self.cull_counters.occluded += occluded;
+        }
+        result
+    }
+
+    /// Tests the bounding volume against the view frustum of the
+    /// model-view-projection matrix, then against the depth buffer, for
+    /// draws with custom shaders to skip invisible meshes. Returns whether
+    /// the mesh is outside or occluded, and counts it in the
+    /// `cull_counters`.
+    pub fn cull(&mut self, bounds: &BoundingVolume, mvp: mat4<f32>) -> bool {
+        self.cull_counters.tested += 1;
+        if bounds.is_outside(&mvp) {
+            self.cull_counters.culled += 1;
+            return true;
+        }
+        if self.is_occluded(bounds, mvp) {
+            self.cull_counters.occluded += 1;
+            return true;
+        }
+        false
+    }
+
+    /// Whether every sample the bounding volume moved by the
+    /// model-view-projection matrix could cover is sure to fail the depth
+    /// test against the depth buffer of the current framebuffer. Only looks
+    /// at the depth ranges of its blocks of pixels, so it errs on the side
+    /// of visibility. A volume reaching behind the camera is never occluded,
+    /// and neither is anything while a depth bias is set.
+    pub fn is_occluded(&self, bounds: &BoundingVolume, mvp: mat4<f32>) -> bool {
+        if !self.stencil_state.keeps_failed() || self.depth_state.bias != DepthBias::default() {
+            return false;
+        }
+        let mut window_min = v2::new(f32::INFINITY, f32::INFINITY);
+        let mut window_max = -window_min;
+        let mut depths = DepthRange::empty();
+        for corner in bounds.corners().iter() {
+            let clip = mvp * v4::new(corner.x, corner.y, corner.z, 1.);
+            if clip.w <= 0. || clip.w.is_nan() {
+                return false;
+            }
+            let ndc = clip.xyz() / clip.w;
+            let window = self.viewport.transform(ndc.xy());
+            if !(window.iter().all(|c| c.is_finite()) && ndc.z.is_finite()) {
+                return false;
+            }
+            window_min = window_min.zip_map(&window, f32::min);
+            window_max = window_max.zip_map(&window, f32::max);
+            depths.include(ndc.z);
+        }
+
+        let (min, max) = self.scissor();
+        let min = v2::new(
+            std::cmp::max(window_min.x.floor() as i32, min.x),
+            std::cmp::max(window_min.y.floor() as i32, min.y));
+        let max = v2::new(
+            std::cmp::min(window_max.x.floor() as i32, max.x),
+            std::cmp::min(window_max.y.floor() as i32, max.y));
+        if min.x > max.x || min.y > max.y {
+            return true;
+        }
+        let hiz = self.framebuffer.hiz();
+        let compare = self.depth_state.compare;
+        for ty in min.y / TILE_SIZE..=max.y / TILE_SIZE {
+            for tx in min.x / TILE_SIZE..=max.x / TILE_SIZE {
+                if compare.rejects(depths, hiz.tile(v2::new(tx, ty))) {
+                    continue;
+                }
+                let from = v2::new(
+                    std::cmp::max(tx * TILE_SIZE, min.x) / BLOCK_SIZE,
+                    std::cmp::max(ty * TILE_SIZE, min.y) / BLOCK_SIZE);
+                let to = v2::new(
+                    std::cmp::min(tx * TILE_SIZE + TILE_SIZE - 1, max.x) / BLOCK_SIZE,
+                    std::cmp::min(ty * TILE_SIZE + TILE_SIZE - 1, max.y) / BLOCK_SIZE);
+                for by in from.y..=to.y {
+                    for bx in from.x..=to.x {
+                        if !compare.rejects(depths, hiz.block(v2::new(bx, by))) {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Counts the samples passing the depth and stencil tests during the
+    /// draws of `f`, which stops at the first draw that fails.
+    ///
+    /// Drawing the bounding box of a room with depth writes disabled and
+    /// `BlendState::keep` leaves every buffer as is and tells whether any of
+    /// the room would show.
+    pub fn occlusion_query<F: FnOnce(&mut Self) -> Result<(), RenderError>>(&mut self, f: F) -> Result<u64, RenderError> {
+        let before = self.samples_passed;
+        f(self)?;
+        Ok(self.samples_passed - before)
+    }
+
+    /// Draws and instances tested against the view frustum since the last
+    /// `reset_cull_counters`.
+    pub fn cull_counters(&self) -> CullCounters {
+        self.cull_counters
+    }
+
+    pub fn reset_cull_counters(&mut self) {
+        self.cull_counters = CullCounters::default();
+    }
+
+    /// Times and counts of the draws since the last `reset_frame_stats`,
+    /// usually queried and reset once per frame.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    pub fn reset_frame_stats(&mut self) {
+        self.frame_stats = FrameStats::default();
+    }
+
+    /// Enables timing the stages of the draws in the `frame_stats`. It's off
+    /// by default, as timing every quad of pixels slows them down
+    /// noticeably. The counts are kept either way.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiling = enabled;
+    }
+
+    /// Draws textured primitives with the fixed `TexturedShader`, see
+    /// `draw_shaded`.
+    pub fn draw<I: Index>(
+        &mut self,
+        ivertices   : &[IVertex],
+        indices     : &[I],
+        topology    : PrimitiveTopology,
+        texture     : &Texture,
+        model_mat   : mat4<f32>) -> Result<(), RenderError> {
+        Self::validate(ivertices, model_mat)?;
+        let shader = TexturedShader {
+            mvp         : self.projection.matrix() * model_mat,
+            texture,
+        };
+        self.draw_shaded(&shader, &shader, ivertices, indices, topology)
+    }
+
+    /// Draws the primitives made of the indexed vertices in three steps: the
+    /// vertices are shaded and the primitives are clipped, set up and binned
+    /// into screen tiles first, then the tiles are shaded in parallel, and
+    /// within a tile the primitives are drawn in the order of `indices`.
+    ///
+    /// Intermediate results live in buffers owned by the renderer, so once
+    /// they have grown to fit the scene, drawing on a single thread doesn't
+    /// touch the heap.
+    ///
+    /// A fragment shader outputting a `GSample` makes this the geometry pass
+    /// of deferred shading: it fills the G-buffer of the framebuffer instead
+    /// of its colors, see `light`.
+    ///
+    /// Fails without drawing anything if an index is out of range, if the
+    /// indices don't make whole primitives, if a triangle of a list repeats
+    /// a vertex, if a vertex position isn't finite or if the G-buffer is
+    /// missing.
+    pub fn draw_shaded<VS, FS, I, C>(
+        &mut self,
+        vertex_shader   : &VS,
+        fragment_shader : &FS,
+        inputs          : &[VS::Input],
+        indices         : &[I],
+        topology        : PrimitiveTopology) -> Result<(), RenderError>
+    where
+        VS: VertexShader,
+        FS: FragmentShader<C, Varyings = VS::Varyings>,
+        I: Index,
+        C: RenderTarget,
+    {
+        Self::check_topology(indices, topology)?;
+        Self::check_indices(indices, inputs.len())?;
+
+        self.execute(fragment_shader, |julia, scratch| {
+            julia.shade_vertices(scratch, inputs, |input| vertex_shader.shade(input))?;
+            julia.assemble(scratch, indices, topology);
+            Ok(())
+        })
+    }
+
+    /// Draws a copy of the primitives for every instance, see `draw_shaded`.
+    /// The vertices of every copy go through `vertex_shader` along with their
+    /// instance, and all the copies are binned and rasterized together, in
+    /// the order of `instances`.
+    pub fn draw_instanced<VS, FS, I, C>(
+        &mut self,
+        vertex_shader   : &VS,
+        fragment_shader : &FS,
+        inputs          : &[VS::Input],
+        instances       : &[VS::Instance],
+        indices         : &[I],
+        topology        : PrimitiveTopology) -> Result<(), RenderError>
+    where
+        VS: InstancedVertexShader,
+        FS: FragmentShader<C, Varyings = VS::Varyings>,
+        I: Index,
+        C: RenderTarget,
+    {
+        Self::check_topology(indices, topology)?;
+        Self::check_indices(indices, inputs.len())?;
+
+        self.execute(fragment_shader, |julia, scratch| {
+            for instance in instances {
+                julia.shade_vertices(scratch, inputs, |input| vertex_shader.shade(input, instance))?;
+                julia.assemble(scratch, indices, topology);
+            }
+            Ok(())
+        })
+    }
+
+    fn check_topology<I: Index>(indices: &[I], topology: PrimitiveTopology) -> Result<(), RenderError> {
+        let whole = match topology {
+            PrimitiveTopology::PointList { size } => {
+                if !(size.is_finite() && size > 0.) {
+                    return Err(RenderError::InvalidPointSize { size });
+                }
+                true
+            },
+            PrimitiveTopology::LineList     => indices.len().is_multiple_of(2),
+            PrimitiveTopology::TriangleList => indices.len().is_multiple_of(3),
+            _                               => true,
+        };
+        if !whole {
+            return Err(RenderError::IndexCountMismatch { index_count: indices.len(), topology });
+        }
+        if topology == PrimitiveTopology::TriangleList {
+            for (triangle, face) in indices.chunks_exact(3).enumerate() {
+                Self::check_triangle(triangle, face[0], face[1], face[2])?;
+            }
+        }
+        Ok(())
+    }
 
+    fn check_triangle<I: Index>(triangle: usize, a: I, b: I, c: I) -> Result<(), RenderError> {
+        let (a, b, c) = (a.to_usize(), b.to_usize(), c.to_usize());
+        if a == b || b == c || c == a {
+            return Err(RenderError::DegenerateTriangle { triangle });
+        }
+        Ok(())
+    }
+
+    /// Lets `process` shade the vertices and turn them into primitives, then
+    /// rasterizes the primitives unless `process` failed.
+    fn execute<V, FS, C, P>(&mut self, fragment_shader: &FS, process: P) -> Result<(), RenderError>
+    where
+        V: Varyings,
+        FS: FragmentShader<C, Varyings = V>,
+        C: RenderTarget,
+        P: FnOnce(&Self, &mut Scratch<V>) -> Result<(), RenderError>,
+    {
+        if C::buffers(&mut self.framebuffer).is_none() {
+            return Err(RenderError::MissingGBuffer);
+        }
+        let mut scratch = self.take_scratch::<V>();
+        scratch.primitives.clear();
+        scratch.stats = FrameStats::default();
+        let mut watch = Stopwatch::start(self.profiling);
+        let result = process(self, &mut scratch);
+        // Vertices are shaded while the primitives are assembled.
+        watch.lap(&mut scratch.stats.setup);
+        scratch.stats.setup = scratch.stats.setup.checked_sub(scratch.stats.vertex).unwrap_or_default();
+        if result.is_ok() {
+            let Scratch { primitives, stats, .. } = &mut *scratch;
+            self.rasterize(primitives, fragment_shader, stats);
+        }
+        self.frame_stats.merge(&scratch.stats);
+        self.frame_stats.pixels = (self.framebuffer.shape().x * self.framebuffer.shape().y) as u64;
+        self.samples_passed += scratch.stats.samples_passed;
+        self.scratch.insert(TypeId::of::<V>(), scratch);
+        result
+    }
+
+    /// Replaces the scratch vertices with the inputs shaded by `shade`, a
+    /// vertex shader bound to the instance if any.
+    fn shade_vertices<In, V, S: Fn(&In) -> (v4<f32>, V)>(
+        &self,
+        scratch         : &mut Scratch<V>,
+        inputs          : &[In],
+        shade           : S) -> Result<(), RenderError> {
+        let mut watch = Stopwatch::start(self.profiling);
+        scratch.vertices.clear();
+        scratch.vertices.extend(inputs.iter().map(|input| {
+            let (coords, varyings) = shade(input);
+            Vertex {
+                coords,
+                varyings,
+            }
+        }));
+        watch.lap(&mut scratch.stats.vertex);
+        Self::check_vertices(&scratch.vertices)
+    }
+
+    fn check_vertices<V>(vertices: &[Vertex<V>]) -> Result<(), RenderError> {
+        match vertices.iter().position(|v| !v.coords.iter().all(|c| c.is_finite())) {
+            Some(index) => Err(RenderError::NonFiniteVertex { index }),
+            None        => Ok(()),
+        }
+    }
 
-            let basis = [&vertices[*a as usize], 
-                         &vertices[*b as usize],
-                         &vertices[*c as usize]];
+    fn take_scratch<V: Varyings>(&mut self) -> Box<Scratch<V>> {
+        match self.scratch.remove(&TypeId::of::<V>()) {
+            Some(scratch)   => scratch.downcast().expect("Scratch buffers are keyed by their type"),
+            None            => Box::new(Scratch {
+                vertices        : vec![],
+                primitives      : vec![],
+                stats           : FrameStats::default(),
+            }),
+        }
+    }
 
-            // let now = Instant::now();                                   // TIME MES BEGIN
-            let rasters = self.rasterize_polygon(&basis);
-            // time_acc_ms += now.elapsed().as_millis();                   // LOCAL MES TO ACC
-            let fragments = self.fragment(rasters);
-            for frag in fragments {
-                let index = self.buff_offset(frag.window_coords);
-                if index < self.color_buffer.len() && frag.depth > self.depth_buffer[index] {
-                    self.depth_buffer[index] = frag.depth;
-                    self.color_buffer[index] = texture.get_pixel(frag.tex_coords);
+    fn assemble<V: Varyings, I: Index>(&self, scratch: &mut Scratch<V>, indices: &[I], topology: PrimitiveTopology) {
+        match topology {
+            PrimitiveTopology::PointList { size } => {
+                for i in indices {
+                    self.assemble_point(scratch, i.to_usize(), size);
+                }
+            },
+            PrimitiveTopology::LineList => {
+                for line in indices.chunks_exact(2) {
+                    self.assemble_line(scratch, [line[0].to_usize(), line[1].to_usize()]);
+                }
+            },
+            PrimitiveTopology::LineStrip => {
+                for line in indices.windows(2) {
+                    self.assemble_line(scratch, [line[0].to_usize(), line[1].to_usize()]);
                 }
+            },
+            PrimitiveTopology::TriangleList => {
+                for face in indices.chunks_exact(3) {
+                    self.assemble_triangle(scratch, [face[0].to_usize(), face[1].to_usize(), face[2].to_usize()]);
+                }
+            },
+            PrimitiveTopology::TriangleStrip => {
+                for (i, face) in indices.windows(3).enumerate() {
+                    if i % 2 == 0 {
+                        self.assemble_triangle(scratch, [face[0].to_usize(), face[1].to_usize(), face[2].to_usize()]);
+                    } else {
+                        // Keeps the winding and the first vertex, which provokes.
+                        self.assemble_triangle(scratch, [face[0].to_usize(), face[2].to_usize(), face[1].to_usize()]);
+                    }
+                }
+            },
+            PrimitiveTopology::TriangleFan => {
+                for i in 2..indices.len() {
+                    self.assemble_triangle(scratch, [indices[0].to_usize(), indices[i - 1].to_usize(), indices[i].to_usize()]);
+                }
+            },
+        }
+    }
+
+    fn assemble_faces<V: Varyings, I: Index>(&self, scratch: &mut Scratch<V>, faces: &[(I, I, I)]) {
+        for (a, b, c) in faces {
+            self.assemble_triangle(scratch, [a.to_usize(), b.to_usize(), c.to_usize()]);
+        }
+    }
+
+    fn check_faces<I: Index>(faces: &[(I, I, I)], vertex_count: usize) -> Result<(), RenderError> {
+        for (triangle, (a, b, c)) in faces.iter().enumerate() {
+            Self::check_indices(&[*a, *b, *c], vertex_count)?;
+            Self::check_triangle(triangle, *a, *b, *c)?;
+        }
+        Ok(())
+    }
+
+    fn check_indices<I: Index>(indices: &[I], vertex_count: usize) -> Result<(), RenderError> {
+        for index in indices {
+            let index = index.to_usize();
+            if index >= vertex_count {
+                return Err(RenderError::IndexOutOfRange { index, vertex_count });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the vertices and the model matrix of a draw call are finite.
+    fn validate(ivertices: &[IVertex], model_mat: mat4<f32>) -> Result<(), RenderError> {
+        for (index, iv) in ivertices.iter().enumerate() {
+            if !(iv.coords.iter().all(|c| c.is_finite()) && iv.tex_coords.iter().all(|c| c.is_finite())) {
+                return Err(RenderError::NonFiniteVertex { index });
             }
+        }
+        if !model_mat.iter().all(|c| c.is_finite()) {
+            return Err(RenderError::NonFiniteTransform);
+        }
+        Ok(())
+    }
+
+    fn assemble_triangle<V: Varyings>(&self, scratch: &mut Scratch<V>, indices: [usize; 3]) {
+        let mut triangle = [scratch.vertices[indices[0]],
+                            scratch.vertices[indices[1]],
+                            scratch.vertices[indices[2]]];
+        // The first vertex provokes, so clipping can't lose its flat values.
+        let provoking = triangle[0].varyings;
+        triangle[1].varyings.flatten(&provoking);
+        triangle[2].varyings.flatten(&provoking);
+        scratch.stats.triangles_submitted += 1;
+        if !triangle.iter().all(clip::is_inside) {
+            scratch.stats.triangles_clipped += 1;
+        }
+        let mut polygon = clip::clip_triangle(triangle);
+        for v in polygon.vertices_mut() {
+            *v = Self::perspective_divide(*v);
+        }
+
+        let (edges, polygon) = (polygon.edges(), polygon.vertices());
+        let n = polygon.len();
+        for i in 1..n.saturating_sub(1) {
+            let outline = [i == 1 && edges[0], edges[i], i + 2 == n && edges[n - 1]];
+            match self.setup([polygon[0], polygon[i], polygon[i + 1]], outline) {
+                Some(triangle)  => scratch.primitives.push(Primitive::Triangle(triangle)),
+                None            => scratch.stats.triangles_culled += 1,
+            }
+        }
+    }
 
+    fn assemble_line<V: Varyings>(&self, scratch: &mut Scratch<V>, indices: [usize; 2]) {
+        let mut line = [scratch.vertices[indices[0]], scratch.vertices[indices[1]]];
+        let provoking = line[0].varyings;
+        line[1].varyings.flatten(&provoking);
+        if let Some(line) = clip::clip_line(line) {
+            let vertices = [Self::perspective_divide(line[0]), Self::perspective_divide(line[1])];
+            if let Some(line) = self.setup_line(vertices) {
+                scratch.primitives.push(Primitive::Line(line));
+            }
         }
-        // println!("Time elapsed {}ms", time_acc_ms);
     }
 
-    fn geometry_2(&self, iverts: &[IVertex], model: mat4<f32>) -> Vec<Vertex> {
-        iverts.iter().map(|iv| Self::shader_2(*iv, model)).collect()
+    fn assemble_point<V: Varyings>(&self, scratch: &mut Scratch<V>, index: usize, size: f32) {
+        let vertex = scratch.vertices[index];
+        if clip::is_inside(&vertex) {
+            if let Some(point) = self.setup_point(Self::perspective_divide(vertex), size) {
+                scratch.primitives.push(Primitive::Point(point));
+            }
+        }
     }
 
-    fn shader_2(iv: IVertex, model: mat4<f32>) -> Vertex {
-        let coords = v4::new(iv.coords.x, 
-                             iv.coords.y,
-                             iv.coords.z,
-                             1.);
+    /// Bins the assembled primitives and shades them tile by tile, adding
+    /// what it takes to `stats`.
+    fn rasterize<V: Varyings, C: RenderTarget, FS: FragmentShader<C, Varyings = V>>(
+        &mut self,
+        primitives  : &[Primitive<V>],
+        shader      : &FS,
+        stats       : &mut FrameStats) {
+        let mut watch = Stopwatch::start(self.profiling);
+        self.bins.reshape(self.framebuffer.shape());
+        self.bins.clear();
+        for (i, primitive) in primitives.iter().enumerate() {
+            let (min, max) = primitive.bounds();
+            self.bins.insert(i, min, max);
+        }
+        watch.lap(&mut stats.setup);
+
+        let width = self.framebuffer.shape().x;
+        let attachments = C::buffers(&mut self.framebuffer)
+            .expect("Draws check that the framebuffer has the buffer");
+        let sample_pattern = attachments.sample_pattern;
+        let samples = sample_pattern.samples();
+        let bins = &self.bins;
+        let polygon_mode = self.polygon_mode;
+        let depth_state = self.depth_state;
+        let stencil_state = self.stencil_state;
+        let blend_state = self.blend_state;
+        let profiling = self.profiling;
+        let tile_stats = Mutex::new(FrameStats::default());
+        let shade_tile = |coords: v2<i32>, buffers: TileBuffers<C>| {
+            let bin = bins.bin(coords);
+            if bin.is_empty() {
+                return;
+            }
+            let (min, max) = bins.rect(coords);
+            let mut tile = Tile {
+                origin          : min,
+                samples,
+                depth_state,
+                stencil_state,
+                blend_state,
+                color_rows      : buffers.color,
+                depth_rows      : buffers.depth,
+                stencil_rows    : buffers.stencil,
+                hiz             : HiZTile::new(buffers.hiz, min, max - min + v2::new(1, 1), samples),
+                profiling,
+                stats           : FrameStats::default(),
+            };
+            let (mut shading, mut hiz_update) = Default::default();
+            let mut watch = Stopwatch::start(profiling);
+            if polygon_mode == PolygonMode::HiddenLine {
+                for i in bin {
+                    if let Primitive::Triangle(triangle) = &primitives[*i] {
+                        Self::hide_triangle(&mut tile, triangle, min, max, sample_pattern);
+                    }
+                }
+            }
+            for i in bin {
+                match (&primitives[*i], polygon_mode) {
+                    (Primitive::Triangle(triangle), PolygonMode::Fill) => Self::shade_triangle(
+                        &mut tile, triangle, min, max, sample_pattern, shader),
+                    (Primitive::Triangle(triangle), PolygonMode::Line) => Self::shade_outline(
+                        &mut tile, triangle, min, max, shader, 0.),
+                    (Primitive::Triangle(triangle), PolygonMode::HiddenLine) => Self::shade_outline(
+                        &mut tile, triangle, min, max, shader, triangle.outline_tolerance()),
+                    (Primitive::Line(line), _) => Self::shade_line(
+                        &mut tile, line, min, max, shader),
+                    (Primitive::Point(point), _) => Self::shade_point(
+                        &mut tile, point, min, max, sample_pattern, shader),
+                }
+            }
+            watch.lap(&mut shading);
+            if depth_state.write_enabled {
+                Self::update_hiz(&mut tile, bin, primitives, min, max);
+            }
+            watch.lap(&mut hiz_update);
+            // Shading times every quad, what is left is spent walking pixels.
+            let stats = &mut tile.stats;
+            stats.raster = shading.checked_sub(stats.fragment + stats.depth_test).unwrap_or_default();
+            stats.depth_test += hiz_update;
+            tile_stats.lock().unwrap().merge(stats);
+        };
+
+        // Every tile owns its pixels, so any thread can shade any tile.
+        if self.threads.count() > 1 {
+            let shade_tile = &shade_tile;
+            self.threads.install(|| rayon::scope(|scope| {
+                TileBuffers::split(attachments, width, |coords, buffers| {
+                    if !bins.bin(coords).is_empty() {
+                        scope.spawn(move |_| shade_tile(coords, buffers));
+                    }
+                });
+            }));
+        } else {
+            TileBuffers::split(attachments, width, shade_tile);
+        }
+        stats.merge(&tile_stats.into_inner().unwrap());
+    }
 
-        let far     = 1000.;
-        let near    = 0.01;
-        let coords = model * coords;
-        let coords = v4::new(
-            coords.x / coords.z,
-            coords.y / coords.z,
-            -(far + near) / (far - near) - 2. * far * near / (coords.z * (far - near)),
-            coords.z);
+    /// Brings the depth ranges of the tile `[min, max]` up to date once the
+    /// primitives of its bin are drawn, looking only at the pixels they may
+    /// have covered.
+    fn update_hiz<V, C: RenderTarget>(
+        tile            : &mut Tile<C>,
+        bin             : &[usize],
+        primitives      : &[Primitive<V>],
+        min             : v2<i32>,
+        max             : v2<i32>) {
+        if bin.is_empty() {
+            return;
+        }
+        let (mut dirty_min, mut dirty_max) = (max, min);
+        for i in bin {
+            let (from, to) = primitives[*i].bounds();
+            dirty_min = v2::new(std::cmp::min(dirty_min.x, from.x), std::cmp::min(dirty_min.y, from.y));
+            dirty_max = v2::new(std::cmp::max(dirty_max.x, to.x), std::cmp::max(dirty_max.y, to.y));
+        }
+        let dirty_min = v2::new(std::cmp::max(dirty_min.x, min.x), std::cmp::max(dirty_min.y, min.y));
+        let dirty_max = v2::new(std::cmp::min(dirty_max.x, max.x), std::cmp::min(dirty_max.y, max.y));
+        tile.hiz.update(&tile.depth_rows, dirty_min, dirty_max);
+    }
 
+    /// Turns a clipped vertex into normalized device coordinates. The clip-space
+    /// `w` is kept in `coords.w` for perspective-correct interpolation.
+    fn perspective_divide<V>(v: Vertex<V>) -> Vertex<V> {
         Vertex {
-            coords      : coords,
-            tex_coords  : iv.tex_coords,
+            coords      : v4::new(
+                v.coords.x / v.coords.w,
+                v.coords.y / v.coords.w,
+                v.coords.z / v.coords.w,
+                v.coords.w),
+            varyings    : v.varyings,
         }
     }
 
-    fn rasterize_polygon<'a>(&self, basis: &'a Basis) -> Vec<Raster<'a>> {
-        let mut rasters = vec![];
-        rasters.extend(self.rasterize_line(basis, (0, 1)).into_iter());
-        rasters.extend(self.rasterize_line(basis, (1, 2)).into_iter());
-        rasters.extend(self.rasterize_line(basis, (0, 2)).into_iter());
+    /// Maps the triangle onto the window. Returns `None` when it is culled or
+    /// covers no pixel.
+    fn setup<V>(&self, mut vertices: [Vertex<V>; 3], outline: [bool; 3]) -> Option<Triangle<V>> {
+        let dots = [
+            utils::snap(self.viewport.transform(vertices[0].coords.xy())),
+            utils::snap(self.viewport.transform(vertices[1].coords.xy())),
+            utils::snap(self.viewport.transform(vertices[2].coords.xy())),
+        ];
 
-        let mut scanline = vec![None; self.shape.x as usize];
-        let w_2 = self.shape.x / 2;
-        for raster in rasters {
-            let index = raster.window_coords.x + w_2;
-            if index < 0 || index >= self.shape.x {
-                continue;
+        let area = utils::edge(dots[0], dots[1], dots[2]);
+        if area == 0 || self.is_culled(area) {
+            return None;
+        }
+
+        // Walk the vertices counter-clockwise, so the inner side of every edge
+        // is on its left.
+        let order = if area > 0 { [0, 1, 2] } else { [0, 2, 1] };
+        let (a, b, c) = (dots[order[0]], dots[order[1]], dots[order[2]]);
+
+        let (min, max) = self.scissor();
+        // Lines cover every pixel they cross, not only the sampled ones.
+        let (min_offset, max_offset) = match self.polygon_mode {
+            PolygonMode::Fill   => self.framebuffer.sample_pattern().bounds(),
+            _                   => utils::pixel_bounds(),
+        };
+        let min = v2::new(
+            std::cmp::max(utils::first_pixel(a.x.min(b.x).min(c.x), max_offset.x), min.x),
+            std::cmp::max(utils::first_pixel(a.y.min(b.y).min(c.y), max_offset.y), min.y));
+        let max = v2::new(
+            std::cmp::min(utils::last_pixel(a.x.max(b.x).max(c.x), min_offset.x), max.x),
+            std::cmp::min(utils::last_pixel(a.y.max(b.y).max(c.y), min_offset.y), max.y));
+        if min.x > max.x || min.y > max.y {
+            return None;
+        }
+
+        let area = area.abs();
+        let edges = [(b, c), (c, a), (a, b)];
+        let mut depth_slope = v2::new(0., 0.);
+        for k in 0..3 {
+            let (from, to) = edges[k];
+            let z = vertices[order[k]].coords.z;
+            depth_slope.x -= z * ((to.y - from.y) << utils::SUBPIXEL_BITS) as f32 / area as f32;
+            depth_slope.y += z * ((to.x - from.x) << utils::SUBPIXEL_BITS) as f32 / area as f32;
+        }
+        // Depth is interpolated linearly, so moving every vertex moves every
+        // fragment by the same amount.
+        let offset = self.depth_state.bias.offset(depth_slope.x.abs().max(depth_slope.y.abs()));
+        for v in vertices.iter_mut() {
+            v.coords.z += offset;
+        }
+
+        let bias = |a, b| if utils::is_top_left(a, b) { 0 } else { -1 };
+        Some(Triangle {
+            vertices,
+            dots        : [a, b, c],
+            order,
+            outline,
+            bias        : [bias(b, c), bias(c, a), bias(a, b)],
+            area,
+            depth_slope,
+            min,
+            max,
+        })
+    }
+
+    /// Maps the line onto the window. Returns `None` when it crosses no pixel.
+    fn setup_line<V>(&self, mut vertices: [Vertex<V>; 2]) -> Option<Line<V>> {
+        let dots = [
+            utils::snap(self.viewport.transform(vertices[0].coords.xy())),
+            utils::snap(self.viewport.transform(vertices[1].coords.xy())),
+        ];
+        let (a, b) = (utils::pixel_of(dots[0]), utils::pixel_of(dots[1]));
+        let (min, max) = self.scissor();
+        let min = v2::new(
+            std::cmp::max(std::cmp::min(a.x, b.x), min.x),
+            std::cmp::max(std::cmp::min(a.y, b.y), min.y));
+        let max = v2::new(
+            std::cmp::min(std::cmp::max(a.x, b.x), max.x),
+            std::cmp::min(std::cmp::max(a.y, b.y), max.y));
+        if min.x > max.x || min.y > max.y {
+            return None;
+        }
+
+        let offset = self.depth_state.bias.offset(0.);
+        for v in vertices.iter_mut() {
+            v.coords.z += offset;
+        }
+
+        Some(Line {
+            vertices,
+            dots,
+            min,
+            max,
+        })
+    }
+
+    /// Maps the point onto the window. Returns `None` when it covers no pixel.
+    fn setup_point<V>(&self, mut vertex: Vertex<V>, size: f32) -> Option<Point<V>> {
+        let center = utils::snap(self.viewport.transform(vertex.coords.xy()));
+        let half = utils::snap(v2::new(size, size) / 2.);
+        let (from, to) = (center - half, center + half);
+
+        let (min, max) = self.scissor();
+        let (min_offset, max_offset) = self.framebuffer.sample_pattern().bounds();
+        // The square is half-open, so samples on its top and right sides are out.
+        let min = v2::new(
+            std::cmp::max(utils::first_pixel(from.x, max_offset.x), min.x),
+            std::cmp::max(utils::first_pixel(from.y, max_offset.y), min.y));
+        let max = v2::new(
+            std::cmp::min(utils::last_pixel(to.x - 1, min_offset.x), max.x),
+            std::cmp::min(utils::last_pixel(to.y - 1, min_offset.y), max.y));
+        if min.x > max.x || min.y > max.y {
+            return None;
+        }
+
+        vertex.coords.z += self.depth_state.bias.offset(0.);
+
+        Some(Point {
+            vertex,
+            from,
+            to,
+            min,
+            max,
+        })
+    }
+
+    /// Draws the part of the triangle inside the inclusive pixel range
+    /// `[min, max]` of the tile. Blocks of pixels where the whole
+    /// triangle is sure to fail the depth test are skipped.
+    fn shade_triangle<V: Varyings, C: RenderTarget, FS: FragmentShader<C, Varyings = V>>(
+        tile            : &mut Tile<C>,
+        triangle        : &Triangle<V>,
+        min             : v2<i32>,
+        max             : v2<i32>,
+        sample_pattern  : &SamplePattern,
+        shader          : &FS) {
+        let shade_quad = |tile: &mut Tile<C>, quad: &[Raster; 4]| {
+            let mut watch = Stopwatch::start(tile.profiling);
+            let frags = [
+                Self::fragment(&triangle.vertices, quad[0]),
+                Self::fragment(&triangle.vertices, quad[1]),
+                Self::fragment(&triangle.vertices, quad[2]),
+                Self::fragment(&triangle.vertices, quad[3]),
+            ];
+            let mut colors = [None; 4];
+            for (lane, frag) in frags.iter().enumerate() {
+                if frag.coverage == 0 {
+                    continue;
+                }
+                tile.stats.fragments_generated += 1;
+                // Lanes go left to right, then bottom to top.
+                let (row, column) = (lane & 2, lane & 1);
+                let derivatives = Derivatives {
+                    dx  : frags[row + 1].varyings.difference(&frags[row].varyings),
+                    dy  : frags[column + 2].varyings.difference(&frags[column].varyings),
+                };
+                colors[lane] = shader.shade(&frag.varyings, &derivatives);
             }
-            let index = index as usize;
-            scanline[index] = match scanline[index] {
-                None => Some((raster, raster)),
-                Some((rast_min, rast_max)) => Some((
-                    if rast_min.window_coords.y > raster.window_coords.y { raster } else { rast_min },
-                    if rast_max.window_coords.y < raster.window_coords.y { raster } else { rast_max }))
-            }
-        }
-
-        let mut result = vec![];
-        for mb_rasters in scanline {
-            match mb_rasters {
-                None => {
-                    // no-op
-                },
-                Some((rast_min, rast_max)) => {
-                    let x = rast_min.window_coords.x;
-                    let y_min = rast_min.window_coords.y;
-                    let y_max = rast_max.window_coords.y;
-                    for y in y_min..=y_max {
-                        let win_coords = v2::new(x, y);
-                        let (k1, k2) = utils::linear_interpolation(win_coords, rast_min.window_coords, rast_max.window_coords);
-                        result.push(Raster {
-                            window_coords   : win_coords,
-                            baricentric     : k1 * rast_min.baricentric + k2 * rast_max.baricentric,
-                            basis           : basis,
-                        });
+            watch.lap(&mut tile.stats.fragment);
+
+            for (frag, color) in frags.iter().zip(colors.iter()) {
+                let color = match color {
+                    Some(color) => *color,
+                    None        => continue,
+                };
+                let (row, index) = tile.offset(frag.window_coords);
+                let mut passed = false;
+                for (s, offset) in sample_pattern.offsets().iter().enumerate() {
+                    if frag.coverage & (1 << s) == 0 {
+                        continue;
+                    }
+                    let depth = frag.depth + utils::center_distance(*offset).dot(&triangle.depth_slope);
+                    if tile.depth_stencil_test(row, index + s, depth, 0.) {
+                        tile.write_color(row, index + s, color);
+                        passed = true;
                     }
                 }
+                tile.stats.fragments_passed += passed as u64;
+            }
+            watch.lap(&mut tile.stats.depth_test);
+        };
+
+        let depths = triangle.depth_range();
+        if tile.rejects(depths, tile.hiz.tile()) {
+            return;
+        }
+        let min = v2::new(std::cmp::max(min.x, triangle.min.x), std::cmp::max(min.y, triangle.min.y));
+        let max = v2::new(std::cmp::min(max.x, triangle.max.x), std::cmp::min(max.y, triangle.max.y));
+        // Runs of blocks along a row that can't be skipped are rasterized at
+        // once.
+        for by in min.y / BLOCK_SIZE..=max.y / BLOCK_SIZE {
+            let rows = (std::cmp::max(by * BLOCK_SIZE, min.y), std::cmp::min(by * BLOCK_SIZE + BLOCK_SIZE - 1, max.y));
+            let mut run = None;
+            for bx in min.x / BLOCK_SIZE..=max.x / BLOCK_SIZE + 1 {
+                let skipped = bx > max.x / BLOCK_SIZE || tile.rejects(depths, tile.hiz.block(v2::new(bx, by)));
+                match (run, skipped) {
+                    (None, false)       => run = Some(bx),
+                    (Some(first), true) => {
+                        let from = v2::new(std::cmp::max(first * BLOCK_SIZE, min.x), rows.0);
+                        let to = v2::new(std::cmp::min(bx * BLOCK_SIZE - 1, max.x), rows.1);
+                        Self::rasterize_polygon(triangle, from, to, sample_pattern, |quad| shade_quad(tile, quad));
+                        run = None;
+                    },
+                    _                   => {},
+                }
             }
         }
+    }
 
-        result
+    /// Fills the depth of the triangle and hides what is behind it with the
+    /// clear color, leaving the surface blank for its outline.
+    fn hide_triangle<V: Varyings, C: RenderTarget>(
+        tile            : &mut Tile<C>,
+        triangle        : &Triangle<V>,
+        min             : v2<i32>,
+        max             : v2<i32>,
+        sample_pattern  : &SamplePattern) {
+        Self::rasterize_polygon(triangle, min, max, sample_pattern, |quad| {
+            for raster in quad.iter().filter(|raster| raster.coverage != 0) {
+                let frag = Self::fragment(&triangle.vertices, *raster);
+                let (row, index) = tile.offset(frag.window_coords);
+                for (s, offset) in sample_pattern.offsets().iter().enumerate() {
+                    if frag.coverage & (1 << s) == 0 {
+                        continue;
+                    }
+                    let depth = frag.depth + utils::center_distance(*offset).dot(&triangle.depth_slope);
+                    if tile.depth_stencil_test(row, index + s, depth, 0.) {
+                        tile.color_rows[row][index + s] = C::default();
+                    }
+                }
+            }
+        });
     }
 
-    fn rasterize_line<'a>(&self, basis: &'a Basis, map: (usize, usize)) -> Vec<Raster<'a>>{
-        let fst = utils::rasterize_dot(basis[map.0].coords.xy(), self.shape);
-        let snd = utils::rasterize_dot(basis[map.1].coords.xy(), self.shape);
-        utils::line((fst.x, fst.y), (snd.x, snd.y))
-            .into_iter()
-            .map(|(x, y)| {
-                let mut baricentric = v3::new(0., 0., 0.);
-                let (k1, k2) = utils::linear_interpolation(v2::new(x, y), fst, snd);
-                baricentric[map.0] = k1;
-                baricentric[map.1] = k2;
-                Raster {
-                    window_coords   : v2::new(x, y),
-                    baricentric     : baricentric,
-                    basis           : basis,
+    /// Draws the outline of the clipped polygon the triangle belongs to.
+    /// Line fragments within `tolerance` behind the depth buffer still pass,
+    /// so the outline of a filled surface isn't hidden by the surface itself.
+    fn shade_outline<V: Varyings, C: RenderTarget, FS: FragmentShader<C, Varyings = V>>(
+        tile            : &mut Tile<C>,
+        triangle        : &Triangle<V>,
+        min             : v2<i32>,
+        max             : v2<i32>,
+        shader          : &FS,
+        tolerance       : f32) {
+        for k in 0..3 {
+            if !triangle.outline[k] {
+                continue;
+            }
+            let edge = (k, (k + 1) % 3);
+            Self::rasterize_line(triangle.dot(edge.0), triangle.dot(edge.1), min, max, |window_coords, t, dt| {
+                let pixel = LinePixel { window_coords, edge, t, dt, tolerance };
+                Self::shade_line_pixel(tile, &triangle.vertices, pixel, shader);
+            });
+        }
+    }
+
+    fn shade_line<V: Varyings, C: RenderTarget, FS: FragmentShader<C, Varyings = V>>(
+        tile            : &mut Tile<C>,
+        line            : &Line<V>,
+        min             : v2<i32>,
+        max             : v2<i32>,
+        shader          : &FS) {
+        let vertices = [line.vertices[0], line.vertices[1], line.vertices[1]];
+        Self::rasterize_line(line.dots[0], line.dots[1], min, max, |window_coords, t, dt| {
+            let pixel = LinePixel { window_coords, edge: (0, 1), t, dt, tolerance: 0. };
+            Self::shade_line_pixel(tile, &vertices, pixel, shader);
+        });
+    }
+
+    /// Shades the pixel of a line along the edge between two of the
+    /// vertices. Derivatives are taken along the line.
+    ///
+    /// Lines are one pixel wide, so a line fragment covers all the samples of
+    /// its pixel.
+    fn shade_line_pixel<V: Varyings, C: RenderTarget, FS: FragmentShader<C, Varyings = V>>(
+        tile            : &mut Tile<C>,
+        vertices        : &[Vertex<V>; 3],
+        pixel           : LinePixel,
+        shader          : &FS) {
+        let LinePixel { window_coords, edge, t, dt, tolerance } = pixel;
+        let mut watch = Stopwatch::start(tile.profiling);
+        tile.stats.fragments_generated += 1;
+        let full_coverage = ((1_u64 << tile.samples) - 1) as u32;
+        let raster = |t: f32| {
+            let mut baricentric = v3::new(0., 0., 0.);
+            baricentric[edge.0] = 1. - t;
+            baricentric[edge.1] = t;
+            Raster {
+                window_coords,
+                baricentric,
+                coverage        : full_coverage,
+            }
+        };
+        let frag = Self::fragment(vertices, raster(t));
+        let derivatives = Derivatives {
+            dx  : Self::fragment(vertices, raster(t + dt.x)).varyings.difference(&frag.varyings),
+            dy  : Self::fragment(vertices, raster(t + dt.y)).varyings.difference(&frag.varyings),
+        };
+        let color = shader.shade(&frag.varyings, &derivatives);
+        watch.lap(&mut tile.stats.fragment);
+        let color = match color {
+            Some(color) => color,
+            None        => return,
+        };
+        let (row, index) = tile.offset(frag.window_coords);
+        let mut passed = false;
+        for s in 0..tile.samples {
+            if tile.depth_stencil_test(row, index + s, frag.depth, tolerance) {
+                tile.write_color(row, index + s, color);
+                passed = true;
+            }
+        }
+        tile.stats.fragments_passed += passed as u64;
+        watch.lap(&mut tile.stats.depth_test);
+    }
+
+    /// Walks the line between two points in fixed-point window coordinates
+    /// with the Bresenham algorithm. Every pixel inside the inclusive range
+    /// `[min, max]` is passed to `emit` along with how far along the line its
+    /// center is, and how much that changes from one pixel to the next.
+    fn rasterize_line<F: FnMut(v2<i32>, f32, v2<f32>)>(
+        from            : v2<i64>,
+        to              : v2<i64>,
+        min             : v2<i32>,
+        max             : v2<i32>,
+        mut emit        : F) {
+        let (a, b) = (utils::pixel_of(from), utils::pixel_of(to));
+        let d = (to - from).map(|c| c as f32);
+        let length = d.norm_squared();
+        let dt = if length > 0. { d * utils::SUBPIXEL_ONE as f32 / length } else { v2::new(0., 0.) };
+        utils::line((a.x, a.y), (b.x, b.y), (min.x, min.y), (max.x, max.y), |x, y| {
+            // Where the center of the pixel projects onto the line.
+            let t = if length > 0. {
+                let p = (utils::pixel_center(v2::new(x, y)) - from).map(|c| c as f32);
+                (p.dot(&d) / length).clamp(0., 1.)
+            } else {
+                0.
+            };
+            emit(v2::new(x, y), t, dt);
+        });
+    }
+
+    /// Draws the part of the point square inside the inclusive pixel range
+    /// `[min, max]` of the tile. The whole square gets the depth and the
+    /// attributes of the point.
+    fn shade_point<V: Varyings, C: RenderTarget, FS: FragmentShader<C, Varyings = V>>(
+        tile            : &mut Tile<C>,
+        point           : &Point<V>,
+        min             : v2<i32>,
+        max             : v2<i32>,
+        sample_pattern  : &SamplePattern,
+        shader          : &FS) {
+        let mut watch = Stopwatch::start(tile.profiling);
+        let min = v2::new(std::cmp::max(min.x, point.min.x), std::cmp::max(min.y, point.min.y));
+        let max = v2::new(std::cmp::min(max.x, point.max.x), std::cmp::min(max.y, point.max.y));
+        let frag = Self::fragment(&[point.vertex; 3], Raster {
+            window_coords   : min,
+            baricentric     : v3::new(1., 0., 0.),
+            coverage        : 0,
+        });
+        // The whole square has the same varyings.
+        let derivatives = Derivatives {
+            dx  : frag.varyings.difference(&frag.varyings),
+            dy  : frag.varyings.difference(&frag.varyings),
+        };
+        let color = shader.shade(&frag.varyings, &derivatives);
+        watch.lap(&mut tile.stats.fragment);
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let corner = utils::pixel_corner(v2::new(x, y));
+                let (row, index) = tile.offset(v2::new(x, y));
+                let (mut covered, mut passed) = (false, false);
+                for (s, offset) in sample_pattern.offsets().iter().enumerate() {
+                    let p = corner + offset;
+                    let inside = point.from.x <= p.x && p.x < point.to.x
+                              && point.from.y <= p.y && p.y < point.to.y;
+                    covered |= inside;
+                    if let (true, Some(color)) = (inside, color) {
+                        if tile.depth_stencil_test(row, index + s, frag.depth, 0.) {
+                            tile.write_color(row, index + s, color);
+                            passed = true;
+                        }
+                    }
                 }
-            })
-            .collect()
+                tile.stats.fragments_generated += covered as u64;
+                tile.stats.fragments_passed += passed as u64;
+            }
+        }
+        watch.lap(&mut tile.stats.depth_test);
     }
 
-    fn fragment(&self, rs: Vec<Raster>) -> Vec<Fragment> {
-        rs.into_iter().map(|r| {
-            let v1 = r.basis[0];
-            let v2 = r.basis[1];
-            let v3 = r.basis[2];
-            let v1_w = r.baricentric.x / v1.coords.w;
-            let v2_w = r.baricentric.y / v2.coords.w;
-            let v3_w = r.baricentric.z / v3.coords.w;
-            let tex_coords = (v1.tex_coords * v1_w + v2.tex_coords * v2_w + v3.tex_coords * v3_w) / (v1_w + v2_w + v3_w);
-            let depth = v1.coords.z * r.baricentric.x + v2.coords.z * r.baricentric.y + v3.coords.z * r.baricentric.z; // Idk why but it works :|
-            Fragment {
-                window_coords   : r.window_coords,
-                tex_coords      : tex_coords,
-                depth           : depth,
+    /// Rasterizes the triangle with edge functions over its bounding box,
+    /// limited to the inclusive pixel range `[min, max]`, and passes every
+    /// pixel with at least one covered sample to `emit`.
+    ///
+    /// A sample lying exactly on an edge belongs to the triangle only if that
+    /// edge is a top or a left one, so triangles sharing an edge never overlap
+    /// nor leave gaps. Edge functions are evaluated exactly in fixed point and
+    /// stepped incrementally from one pixel corner to the next.
+    fn rasterize_polygon<V, F: FnMut(&[Raster; 4])>(
+        triangle        : &Triangle<V>,
+        min             : v2<i32>,
+        max             : v2<i32>,
+        sample_pattern  : &SamplePattern,
+        mut emit        : F) {
+        let [a, b, c] = triangle.dots;
+        let min = v2::new(std::cmp::max(min.x, triangle.min.x), std::cmp::max(min.y, triangle.min.y));
+        let max = v2::new(std::cmp::min(max.x, triangle.max.x), std::cmp::min(max.y, triangle.max.y));
+
+        // Quads start at even pixels, so the ones of neighbouring tiles and
+        // triangles line up.
+        let quad_min = v2::new(min.x & !1, min.y & !1);
+        let edges = [(b, c), (c, a), (a, b)];
+        let origin = utils::pixel_corner(quad_min);
+        let center = utils::pixel_center(v2::new(0, 0));
+        let mut w_row = [0; 3];
+        let mut step_x = [0; 3];
+        let mut step_y = [0; 3];
+        let mut center_delta = [0; 3];
+        let mut sample_delta = [[0; 3]; MAX_SAMPLES];
+        for k in 0..3 {
+            let (from, to) = edges[k];
+            let (dx, dy) = (-(to.y - from.y), to.x - from.x);
+            w_row[k] = utils::edge(from, to, origin) + triangle.bias[k];
+            step_x[k] = dx << utils::SUBPIXEL_BITS;
+            step_y[k] = dy << utils::SUBPIXEL_BITS;
+            center_delta[k] = dx * center.x + dy * center.y - triangle.bias[k];
+            for (s, offset) in sample_pattern.offsets().iter().enumerate() {
+                sample_delta[s][k] = dx * offset.x + dy * offset.y;
+            }
+        }
+        let sample_delta = &sample_delta[..sample_pattern.samples()];
+
+        for y in (quad_min.y..=max.y).step_by(2) {
+            let mut w = w_row;
+            for x in (quad_min.x..=max.x).step_by(2) {
+                let mut quad = [Raster {
+                    window_coords   : v2::new(x, y),
+                    baricentric     : v3::new(0., 0., 0.),
+                    coverage        : 0,
+                }; 4];
+                for (lane, raster) in quad.iter_mut().enumerate() {
+                    let (dx, dy) = ((lane & 1) as i32, (lane >> 1) as i32);
+                    let mut lane_w = w;
+                    for k in 0..3 {
+                        lane_w[k] += dx as i64 * step_x[k] + dy as i64 * step_y[k];
+                    }
+                    let pixel = v2::new(x + dx, y + dy);
+                    if pixel.x <= max.x && pixel.y <= max.y && min.x <= pixel.x && min.y <= pixel.y {
+                        for (s, delta) in sample_delta.iter().enumerate() {
+                            if lane_w[0] + delta[0] >= 0 && lane_w[1] + delta[1] >= 0 && lane_w[2] + delta[2] >= 0 {
+                                raster.coverage |= 1 << s;
+                            }
+                        }
+                    }
+                    raster.window_coords = pixel;
+                    for k in 0..3 {
+                        raster.baricentric[triangle.order[k]] = (lane_w[k] + center_delta[k]) as f32 / triangle.area as f32;
+                    }
+                }
+                if quad.iter().any(|raster| raster.coverage != 0) {
+                    emit(&quad);
+                }
+                for k in 0..3 {
+                    w[k] += 2 * step_x[k];
+                }
+            }
+            for k in 0..3 {
+                w_row[k] += 2 * step_y[k];
             }
-        }).filter(|f| -1. <= f.depth && f.depth <= 1.).collect()
+        }
+    }
+
+    fn fragment<V: Varyings>(vertices: &[Vertex<V>; 3], r: Raster) -> Fragment<V> {
+        let [v1, v2, v3] = vertices;
+        let perspective = v3::new(
+            r.baricentric.x / v1.coords.w,
+            r.baricentric.y / v2.coords.w,
+            r.baricentric.z / v3.coords.w);
+        let weights = Weights {
+            perspective : perspective / perspective.sum(),
+            linear      : r.baricentric,
+        };
+        let varyings = V::interpolate([&v1.varyings, &v2.varyings, &v3.varyings], weights);
+        // NDC depth is affine in screen space, so it takes the barycentrics as is.
+        let depth = v1.coords.z * r.baricentric.x + v2.coords.z * r.baricentric.y + v3.coords.z * r.baricentric.z;
+        Fragment {
+            window_coords   : r.window_coords,
+            varyings,
+            depth,
+            coverage        : r.coverage,
+        }
+    }
+
+    /// Decides whether the triangle with the given signed window-space area
+    /// is discarded.
+    fn is_culled(&self, area: i64) -> bool {
+        // Window y goes down with a top-left origin, which mirrors the winding.
+        let ccw = (area > 0) == (self.viewport.origin == Origin::BottomLeft);
+        let front = ccw == (self.front_face == FrontFace::Ccw);
+        match self.cull_mode {
+            CullMode::None  => false,
+            CullMode::Front => front,
+            CullMode::Back  => !front,
+        }
     }
 
-    fn buff_offset(&self, window_coords: v2<i32>) -> usize {
-        let coords_u = window_coords + self.shape / 2;
-        (coords_u.x + coords_u.y * self.shape.x) as usize
+    /// Inclusive range of pixels covered by both the viewport and the
+    /// framebuffer.
+    fn scissor(&self) -> (v2<i32>, v2<i32>) {
+        let vp = &self.viewport;
+        let shape = self.framebuffer.shape();
+        (v2::new(std::cmp::max(vp.x, 0),
+                 std::cmp::max(vp.y, 0)),
+         v2::new(std::cmp::min(vp.x + vp.width, shape.x) - 1,
+                 std::cmp::min(vp.y + vp.height, shape.y) - 1))
     }
 }
 
 mod utils {
     use super::*;
 
-    pub fn linear_interpolation(d: v2<i32>, d1: v2<i32>, d2: v2<i32>) -> (f32, f32) {
-        let dx = (d1.x - d2.x).abs();
-        if dx != 0 {
-            let q = (d1.x - d.x).abs() as f32 / dx as f32;
-            (1. - q, q)
-        } else {
-            let dy = (d1.y - d2.y).abs();
-            let q = (d1.y - d.y).abs() as f32 / dy as f32;
-            (1. - q, q)
-        }
+    /// Fractional bits of the fixed-point window coordinates vertices are
+    /// snapped to.
+    pub const SUBPIXEL_BITS: u32 = 8;
+    pub const SUBPIXEL_ONE: i64 = 1 << SUBPIXEL_BITS;
+    const SUBPIXEL_HALF: i64 = SUBPIXEL_ONE / 2;
+
+    /// Rounds window coordinates to the nearest point of the subpixel grid.
+    pub fn snap(dot: v2<f32>) -> v2<i64> {
+        v2::new(
+            (dot.x * SUBPIXEL_ONE as f32).round() as i64,
+            (dot.y * SUBPIXEL_ONE as f32).round() as i64)
+    }
+
+    /// Bottom-left corner of the pixel in fixed-point window coordinates.
+    pub fn pixel_corner(pixel: v2<i32>) -> v2<i64> {
+        v2::new(
+            (pixel.x as i64) << SUBPIXEL_BITS,
+            (pixel.y as i64) << SUBPIXEL_BITS)
     }
 
-    pub fn rasterize_dot(dot: v2<f32>, shape: v2<i32>) -> v2<i32> {
+    /// Pixel containing the point at fixed-point window coordinates.
+    pub fn pixel_of(dot: v2<i64>) -> v2<i32> {
         v2::new(
-            (dot.x * shape.x as f32 / 2.).round() as i32,
-            (dot.y * shape.y as f32 / 2.).round() as i32)
+            dot.x.div_euclid(SUBPIXEL_ONE) as i32,
+            dot.y.div_euclid(SUBPIXEL_ONE) as i32)
+    }
+
+    /// Fixed-point offsets from the pixel corner bounding the whole pixel,
+    /// to use instead of the sample pattern bounds.
+    pub fn pixel_bounds() -> (v2<i64>, v2<i64>) {
+        (v2::new(0, 0), v2::new(SUBPIXEL_ONE - 1, SUBPIXEL_ONE - 1))
+    }
+
+    /// Center of the pixel in fixed-point window coordinates.
+    pub fn pixel_center(pixel: v2<i32>) -> v2<i64> {
+        pixel_corner(pixel) + v2::new(SUBPIXEL_HALF, SUBPIXEL_HALF)
+    }
+
+    /// Vector in pixels from the center of a pixel to the point at the given
+    /// fixed-point offset from its corner.
+    pub fn center_distance(offset: v2<i64>) -> v2<f32> {
+        v2::new(
+            (offset.x - SUBPIXEL_HALF) as f32 / SUBPIXEL_ONE as f32,
+            (offset.y - SUBPIXEL_HALF) as f32 / SUBPIXEL_ONE as f32)
+    }
+
+    /// First pixel which sample at the fixed-point `offset` from its corner
+    /// is not below the fixed-point coordinate.
+    pub fn first_pixel(coord: i64, offset: i64) -> i32 {
+        -(-(coord - offset)).div_euclid(SUBPIXEL_ONE) as i32
+    }
+
+    /// Last pixel which sample at the fixed-point `offset` from its corner is
+    /// not above the fixed-point coordinate.
+    pub fn last_pixel(coord: i64, offset: i64) -> i32 {
+        (coord - offset).div_euclid(SUBPIXEL_ONE) as i32
+    }
+
+    /// Doubled signed area of the triangle `(a, b, p)`: positive when `p` lies
+    /// on the left of the directed edge `a -> b`.
+    pub fn edge(a: v2<i64>, b: v2<i64>, p: v2<i64>) -> i64 {
+        (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
     }
 
-    pub fn line(
+    /// Whether the directed edge `a -> b` of a counter-clockwise triangle is a
+    /// top or a left one.
+    pub fn is_top_left(a: v2<i64>, b: v2<i64>) -> bool {
+        let d = b - a;
+        d.y < 0 || (d.y == 0 && d.x < 0)
+    }
+
+    /// Walks the pixels of the segment from `a` to `b`, both ends included,
+    /// that lie inside the inclusive range `[min, max]`. The walk starts and
+    /// stops where the segment enters and leaves the range along its major
+    /// axis, and plots the same pixels as walking it from `a` would.
+    pub fn line<F: FnMut(i32, i32)>(
         a: (i32, i32),
-        b: (i32, i32)) -> Vec<(i32, i32)> { 
+        b: (i32, i32),
+        min: (i32, i32),
+        max: (i32, i32),
+        plot: F) {
         let dx = b.0 - a.0;
         let dy = b.1 - a.1;
         if dx.abs() >= dy.abs() {
             if dx >= 0 {
-                line_unit_x(a, dx, dy)
+                line_unit_x(a, dx, dy, min, max, plot)
             } else {
-                line_unit_x(b, -dx, -dy)
+                line_unit_x(b, -dx, -dy, min, max, plot)
             }
         } else {
             if dy >= 0 {
-                line_unit_y(a, dx, dy)
+                line_unit_y(a, dx, dy, min, max, plot)
             } else {
-                line_unit_y(b, -dx, -dy)
+                line_unit_y(b, -dx, -dy, min, max, plot)
             }
         }
     }
 
-    fn line_unit_y(
+    /// Steps along the minor axis, and decision variable, of a line with
+    /// `minor <= major` after `k` steps along the major axis.
+    fn line_state(k: i32, major: i32, minor: i32) -> (i32, i32) {
+        let num = 2 * minor * k - major;
+        let steps = if num <= 0 { 0 } else { (num + 2 * major - 1) / (2 * major) };
+        (steps, 2 * minor * (k + 1) - major - 2 * major * steps)
+    }
+
+    fn line_unit_y<F: FnMut(i32, i32)>(
         a: (i32, i32),
         dx: i32,
-        dy: i32) -> Vec<(i32, i32)> {
+        dy: i32,
+        min: (i32, i32),
+        max: (i32, i32),
+        mut plot: F) {
         let (dx, xinc) = if dx >= 0 {
             (dx, 1)
         } else {
             (-dx, -1)
         };
-        let mut x = a.0;
-        let mut d = 2*dx - dy;
-        let mut result = Vec::with_capacity((dx + 1) as usize);
-        for y in a.1..a.1+dy {
-            result.push((x, y,));
+        let first = std::cmp::max(min.1 - a.1, 0);
+        let (steps, mut d) = line_state(first, dy, dx);
+        let mut x = a.0 + xinc * steps;
+        for y in a.1+first..=std::cmp::min(a.1+dy, max.1) {
+            if min.0 <= x && x <= max.0 {
+                plot(x, y);
+            }
             if d > 0 {
                 d -= 2 * dy;
                 x += xinc
             }
             d += 2 * dx;
         }
-        result
     }
 
-    fn line_unit_x(
+    fn line_unit_x<F: FnMut(i32, i32)>(
         a: (i32, i32),
         dx: i32,
-        dy: i32)-> Vec<(i32, i32)> {
+        dy: i32,
+        min: (i32, i32),
+        max: (i32, i32),
+        mut plot: F) {
         let (dy, yinc) = if dy >= 0 {
             (dy, 1)
         } else {
             (-dy, -1)
         };
-        let mut y = a.1;
-        let mut d = 2*dy - dx;
-        let mut result = Vec::with_capacity((dx + 1) as usize);
-        for x in a.0..a.0+dx {
-            result.push((x, y,));
+        let first = std::cmp::max(min.0 - a.0, 0);
+        let (steps, mut d) = line_state(first, dx, dy);
+        let mut y = a.1 + yinc * steps;
+        for x in a.0+first..=std::cmp::min(a.0+dx, max.0) {
+            if min.1 <= y && y <= max.1 {
+                plot(x, y);
+            }
             if d > 0 {
                 d -= 2 * dx;
                 y += yinc;
             }
             d += 2 * dy;
         }
-        result
     }
 }
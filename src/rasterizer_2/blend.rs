@@ -0,0 +1,207 @@
+/// What the source or the destination color is multiplied by before the two
+/// are combined. The source is the color of the fragment, the destination is
+/// the color already in the buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+    ConstantColor,
+    OneMinusConstantColor,
+    ConstantAlpha,
+    OneMinusConstantAlpha,
+}
+
+/// How the weighted source and destination colors are combined. `Min` and
+/// `Max` ignore the factors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendOp {
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+/// How fragments are blended into the color buffer. The color channels and
+/// the alpha channel have their own factors and operation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlendState {
+    pub enabled         : bool,
+    pub src_color       : BlendFactor,
+    pub dst_color       : BlendFactor,
+    pub color_op        : BlendOp,
+    pub src_alpha       : BlendFactor,
+    pub dst_alpha       : BlendFactor,
+    pub alpha_op        : BlendOp,
+    /// Color used by the constant factors.
+    pub constant        : (u8, u8, u8, u8),
+}
+
+impl Default for BlendState {
+    fn default() -> Self {
+        Self {
+            enabled         : false,
+            src_color       : BlendFactor::One,
+            dst_color       : BlendFactor::Zero,
+            color_op        : BlendOp::Add,
+            src_alpha       : BlendFactor::One,
+            dst_alpha       : BlendFactor::Zero,
+            alpha_op        : BlendOp::Add,
+            constant        : (0, 0, 0, 0),
+        }
+    }
+}
+
+impl BlendState {
+    /// Usual blending of a transparent fragment over the buffer.
+    pub fn alpha() -> Self {
+        Self {
+            enabled         : true,
+            src_color       : BlendFactor::SrcAlpha,
+            dst_color       : BlendFactor::OneMinusSrcAlpha,
+            src_alpha       : BlendFactor::One,
+            dst_alpha       : BlendFactor::OneMinusSrcAlpha,
+            ..Self::default()
+        }
+    }
+
+    /// Leaves the color buffer as it is, for draws that only matter for
+    /// their depth or for an occlusion query.
+    pub fn keep() -> Self {
+        Self {
+            enabled         : true,
+            src_color       : BlendFactor::Zero,
+            dst_color       : BlendFactor::One,
+            src_alpha       : BlendFactor::Zero,
+            dst_alpha       : BlendFactor::One,
+            ..Self::default()
+        }
+    }
+
+    /// Blends the fragment color `src` into the buffer color `dst`.
+    pub fn blend(&self, src: (u8, u8, u8, u8), dst: (u8, u8, u8, u8)) -> (u8, u8, u8, u8) {
+        if !self.enabled {
+            return src;
+        }
+        let (s, d, c) = (to_float(src), to_float(dst), to_float(self.constant));
+        let src_color = factor(self.src_color, s, d, c);
+        let dst_color = factor(self.dst_color, s, d, c);
+        let src_alpha = factor(self.src_alpha, s, d, c)[3];
+        let dst_alpha = factor(self.dst_alpha, s, d, c)[3];
+        let mut out = [0.; 4];
+        for i in 0..3 {
+            out[i] = combine(self.color_op, s[i] * src_color[i], d[i] * dst_color[i], s[i], d[i]);
+        }
+        out[3] = combine(self.alpha_op, s[3] * src_alpha, d[3] * dst_alpha, s[3], d[3]);
+        (to_byte(out[0]), to_byte(out[1]), to_byte(out[2]), to_byte(out[3]))
+    }
+}
+
+fn factor(factor: BlendFactor, s: [f32; 4], d: [f32; 4], c: [f32; 4]) -> [f32; 4] {
+    let one_minus = |v: [f32; 4]| [1. - v[0], 1. - v[1], 1. - v[2], 1. - v[3]];
+    match factor {
+        BlendFactor::Zero                   => [0.; 4],
+        BlendFactor::One                    => [1.; 4],
+        BlendFactor::SrcColor               => s,
+        BlendFactor::OneMinusSrcColor       => one_minus(s),
+        BlendFactor::DstColor               => d,
+        BlendFactor::OneMinusDstColor       => one_minus(d),
+        BlendFactor::SrcAlpha               => [s[3]; 4],
+        BlendFactor::OneMinusSrcAlpha       => [1. - s[3]; 4],
+        BlendFactor::DstAlpha               => [d[3]; 4],
+        BlendFactor::OneMinusDstAlpha       => [1. - d[3]; 4],
+        BlendFactor::ConstantColor          => c,
+        BlendFactor::OneMinusConstantColor  => one_minus(c),
+        BlendFactor::ConstantAlpha          => [c[3]; 4],
+        BlendFactor::OneMinusConstantAlpha  => [1. - c[3]; 4],
+    }
+}
+
+/// Combines the weighted channels `s` and `d`. `Min` and `Max` work on the
+/// unweighted channels `src` and `dst`.
+fn combine(op: BlendOp, s: f32, d: f32, src: f32, dst: f32) -> f32 {
+    match op {
+        BlendOp::Add                => s + d,
+        BlendOp::Subtract           => s - d,
+        BlendOp::ReverseSubtract    => d - s,
+        BlendOp::Min                => src.min(dst),
+        BlendOp::Max                => src.max(dst),
+    }
+}
+
+fn to_float(color: (u8, u8, u8, u8)) -> [f32; 4] {
+    [color.0 as f32 / 255., color.1 as f32 / 255., color.2 as f32 / 255., color.3 as f32 / 255.]
+}
+
+fn to_byte(channel: f32) -> u8 {
+    (channel.clamp(0., 1.) * 255.).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(src: BlendFactor, dst: BlendFactor, op: BlendOp) -> BlendState {
+        BlendState {
+            enabled         : true,
+            src_color       : src,
+            dst_color       : dst,
+            color_op        : op,
+            src_alpha       : src,
+            dst_alpha       : dst,
+            alpha_op        : op,
+            ..BlendState::default()
+        }
+    }
+
+    #[test]
+    fn disabled_blending_writes_the_source() {
+        assert_eq!(BlendState::default().blend((1, 2, 3, 4), (5, 6, 7, 8)), (1, 2, 3, 4));
+    }
+
+    #[test]
+    fn half_transparent_red_over_blue() {
+        assert_eq!(BlendState::alpha().blend((255, 0, 0, 128), (0, 0, 255, 255)), (128, 0, 127, 255));
+    }
+
+    #[test]
+    fn keep_leaves_the_destination() {
+        assert_eq!(BlendState::keep().blend((255, 0, 0, 128), (10, 20, 30, 40)), (10, 20, 30, 40));
+    }
+
+    #[test]
+    fn factors_and_operations() {
+        use BlendFactor::{DstAlpha, DstColor, One, OneMinusDstAlpha, Zero};
+        let table = [
+            (state(One, One, BlendOp::Add),                 (200, 100, 0, 255),   (100, 100, 10, 255),  (255, 200, 10, 255)),
+            (state(One, One, BlendOp::Subtract),            (100, 50, 0, 255),    (50, 100, 0, 255),    (50, 0, 0, 0)),
+            (state(One, One, BlendOp::ReverseSubtract),     (100, 50, 0, 255),    (50, 100, 0, 255),    (0, 50, 0, 0)),
+            (state(Zero, Zero, BlendOp::Min),               (100, 50, 200, 10),   (50, 100, 200, 255),  (50, 50, 200, 10)),
+            (state(Zero, Zero, BlendOp::Max),               (100, 50, 200, 10),   (50, 100, 200, 255),  (100, 100, 200, 255)),
+            (state(DstColor, Zero, BlendOp::Add),           (255, 128, 0, 255),   (128, 128, 128, 255), (128, 64, 0, 255)),
+            (state(OneMinusDstAlpha, DstAlpha, BlendOp::Add), (255, 255, 255, 255), (0, 0, 0, 0),       (255, 255, 255, 255)),
+        ];
+        for &(state, src, dst, out) in table.iter() {
+            assert_eq!(state.blend(src, dst), out, "{:?}", state);
+        }
+    }
+
+    #[test]
+    fn constant_alpha() {
+        let state = BlendState {
+            src_color       : BlendFactor::ConstantAlpha,
+            dst_color       : BlendFactor::OneMinusConstantAlpha,
+            constant        : (0, 0, 0, 64),
+            ..BlendState::alpha()
+        };
+        assert_eq!(state.blend((255, 255, 255, 255), (0, 0, 0, 255)), (64, 64, 64, 255));
+    }
+}
@@ -0,0 +1,118 @@
+use nalgebra as na;
+use na::Vector4 as v4;
+use na::Vector3 as v3;
+use na::Matrix4 as mat4;
+use super::IVertex;
+use super::clip::PLANES;
+
+/// Volume enclosing every vertex of a mesh, in model space.
+#[derive(Clone, Copy, Debug)]
+pub enum BoundingVolume {
+    Aabb { min: v3<f32>, max: v3<f32> },
+    Sphere { center: v3<f32>, radius: f32 },
+}
+
+impl BoundingVolume {
+    /// Smallest axis-aligned box around the vertices.
+    pub fn aabb(ivertices: &[IVertex]) -> Self {
+        let (min, max) = Self::extent(ivertices);
+        BoundingVolume::Aabb { min, max }
+    }
+
+    /// Sphere around the vertices, centered on their bounding box. Not the
+    /// smallest one, but never much bigger.
+    pub fn sphere(ivertices: &[IVertex]) -> Self {
+        let (min, max) = Self::extent(ivertices);
+        let center = (min + max) / 2.;
+        let radius = ivertices.iter()
+            .map(|iv| (iv.coords - center).norm())
+            .fold(0., f32::max);
+        BoundingVolume::Sphere { center, radius }
+    }
+
+    fn extent(ivertices: &[IVertex]) -> (v3<f32>, v3<f32>) {
+        let mut min = v3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = -min;
+        for iv in ivertices {
+            min = min.zip_map(&iv.coords, f32::min);
+            max = max.zip_map(&iv.coords, f32::max);
+        }
+        (min, max)
+    }
+
+    /// Corners of the box around the volume.
+    pub fn corners(&self) -> [v3<f32>; 8] {
+        let (min, max) = match *self {
+            BoundingVolume::Aabb { min, max }           => (min, max),
+            BoundingVolume::Sphere { center, radius }   => {
+                let half = v3::new(radius, radius, radius);
+                (center - half, center + half)
+            },
+        };
+        let corner = |i: usize| v3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z });
+        [corner(0), corner(1), corner(2), corner(3), corner(4), corner(5), corner(6), corner(7)]
+    }
+
+    /// Whether the volume lies entirely outside of the view frustum of the
+    /// model-view-projection matrix. Errs on the side of visibility: a volume
+    /// near a corner of the frustum may pass although it is outside.
+    pub fn is_outside(&self, mvp: &mat4<f32>) -> bool {
+        // `dot(plane, mvp * p) == dot(mvp^T * plane, p)`, so the planes can be
+        // moved to model space instead of the volume to clip space.
+        let transposed = mvp.transpose();
+        PLANES.iter().any(|plane| {
+            let plane = transposed * v4::new(plane[0], plane[1], plane[2], plane[3]);
+            let normal = plane.xyz();
+            match *self {
+                BoundingVolume::Aabb { min, max } => {
+                    // The corner furthest along the normal.
+                    let corner = v3::new(
+                        if normal.x > 0. { max.x } else { min.x },
+                        if normal.y > 0. { max.y } else { min.y },
+                        if normal.z > 0. { max.z } else { min.z });
+                    normal.dot(&corner) + plane.w < 0.
+                },
+                BoundingVolume::Sphere { center, radius } => {
+                    normal.dot(&center) + plane.w < -radius * normal.norm()
+                },
+            }
+        })
+    }
+}
+
+/// How many draws, or instances of instanced draws, were tested against the
+/// view frustum and the depth buffer, and how many of them were skipped.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CullCounters {
+    pub tested      : u64,
+    /// Skipped for being outside of the view frustum.
+    pub culled      : u64,
+    /// Skipped for being hidden by what was drawn before.
+    pub occluded    : u64,
+}
+
+/// Mesh drawn by `render_mesh`, carrying its bounding volume so that it can
+/// be skipped when off-screen or hidden.
+pub struct Mesh<'a, I> {
+    pub vertices    : &'a [IVertex],
+    pub faces       : &'a [(I, I, I)],
+    pub bounds      : BoundingVolume,
+}
+
+impl<'a, I> Mesh<'a, I> {
+    /// Mesh bounded by the box around its vertices.
+    pub fn new(vertices: &'a [IVertex], faces: &'a [(I, I, I)]) -> Self {
+        Mesh {
+            vertices,
+            faces,
+            bounds      : BoundingVolume::aabb(vertices),
+        }
+    }
+
+    pub fn with_bounds(self, bounds: BoundingVolume) -> Self {
+        Mesh { bounds, ..self }
+    }
+}
@@ -0,0 +1,192 @@
+use nalgebra as na;
+use na::Vector4 as v4;
+use super::Vertex;
+use crate::julia::Varyings;
+
+/// Planes bounding the canonical view volume `-w <= x, y, z <= w`.
+/// A point lies inside a plane when `dot(plane, coords) >= 0`.
+pub const PLANES: [[f32; 4]; 6] = [
+    [ 1.,  0.,  0.,  1.],   // left
+    [-1.,  0.,  0.,  1.],   // right
+    [ 0.,  1.,  0.,  1.],   // bottom
+    [ 0., -1.,  0.,  1.],   // top
+    [ 0.,  0.,  1.,  1.],   // far
+    [ 0.,  0., -1.,  1.],   // near
+];
+
+/// Every plane cuts at most one corner off a convex polygon, adding a vertex.
+const MAX_VERTICES: usize = 3 + PLANES.len();
+
+/// Convex polygon living on the stack.
+#[derive(Clone, Copy)]
+pub struct Polygon<V> {
+    vertices    : [Vertex<V>; MAX_VERTICES],
+    /// Whether the edge from every vertex to the next one lies on an edge of
+    /// the triangle, rather than on a clipping plane.
+    edges       : [bool; MAX_VERTICES],
+    len         : usize,
+}
+
+impl<V: Varyings> Polygon<V> {
+    fn empty(filler: Vertex<V>) -> Self {
+        Polygon { vertices: [filler; MAX_VERTICES], edges: [false; MAX_VERTICES], len: 0 }
+    }
+
+    fn push(&mut self, v: Vertex<V>, edge: bool) {
+        self.vertices[self.len] = v;
+        self.edges[self.len] = edge;
+        self.len += 1;
+    }
+
+    pub fn vertices(&self) -> &[Vertex<V>] { &self.vertices[..self.len] }
+
+    pub fn vertices_mut(&mut self) -> &mut [Vertex<V>] { &mut self.vertices[..self.len] }
+
+    /// Whether the edge from every vertex to the next one is part of the
+    /// triangle that was clipped.
+    pub fn edges(&self) -> &[bool] { &self.edges[..self.len] }
+}
+
+/// Clips a clip-space triangle against the view frustum with the
+/// Sutherland–Hodgman algorithm.
+///
+/// Returns the remaining convex polygon in the winding order of the
+/// triangle. The polygon is empty when the triangle is entirely outside.
+/// Edges cut along a plane are flagged, so that wireframes leave them out.
+pub fn clip_triangle<V: Varyings>(triangle: [Vertex<V>; 3]) -> Polygon<V> {
+    let mut polygon = Polygon::empty(triangle[0]);
+    for v in triangle.iter() {
+        polygon.push(*v, true);
+    }
+
+    let mut clipped = Polygon::empty(triangle[0]);
+    for plane in PLANES.iter() {
+        let plane = v4::new(plane[0], plane[1], plane[2], plane[3]);
+        clipped.len = 0;
+        for i in 0..polygon.len {
+            let cur  = polygon.vertices[i];
+            let next = polygon.vertices[(i + 1) % polygon.len];
+            let edge = polygon.edges[i];
+            let cur_dist  = plane.dot(&cur.coords);
+            let next_dist = plane.dot(&next.coords);
+            if cur_dist >= 0. {
+                clipped.push(cur, edge);
+            }
+            if (cur_dist >= 0.) != (next_dist >= 0.) {
+                // Leaving the plane, the polygon follows it to where the
+                // edge coming back in crosses it.
+                let t = cur_dist / (cur_dist - next_dist);
+                clipped.push(cur.lerp(&next, t), edge && cur_dist < 0.);
+            }
+        }
+        std::mem::swap(&mut polygon, &mut clipped);
+        if polygon.len < 3 {
+            polygon.len = 0;
+            break;
+        }
+    }
+    polygon
+}
+
+/// Clips a clip-space line against the view frustum with the Liang–Barsky
+/// algorithm. Returns `None` when the line is entirely outside.
+pub fn clip_line<V: Varyings>(line: [Vertex<V>; 2]) -> Option<[Vertex<V>; 2]> {
+    let (mut t_min, mut t_max) = (0., 1.);
+    for plane in PLANES.iter() {
+        let plane = v4::new(plane[0], plane[1], plane[2], plane[3]);
+        let from_dist = plane.dot(&line[0].coords);
+        let to_dist   = plane.dot(&line[1].coords);
+        if from_dist < 0. && to_dist < 0. {
+            return None;
+        }
+        if from_dist < 0. {
+            t_min = f32::max(t_min, from_dist / (from_dist - to_dist));
+        } else if to_dist < 0. {
+            t_max = f32::min(t_max, from_dist / (from_dist - to_dist));
+        }
+    }
+    if t_min > t_max {
+        return None;
+    }
+    Some([line[0].lerp(&line[1], t_min), line[0].lerp(&line[1], t_max)])
+}
+
+/// Whether the clip-space vertex is inside the view frustum.
+pub fn is_inside<V>(v: &Vertex<V>) -> bool {
+    PLANES.iter().all(|plane| v4::new(plane[0], plane[1], plane[2], plane[3]).dot(&v.coords) >= 0.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Vertex carrying its own clip-space position as varyings.
+    fn vertex(x: f32, y: f32, z: f32, w: f32) -> Vertex<v4<f32>> {
+        let coords = v4::new(x, y, z, w);
+        Vertex { coords, varyings: coords }
+    }
+
+    fn assert_near(a: v4<f32>, b: v4<f32>) {
+        assert!((a - b).norm() < 1e-5, "{:?} isn't {:?}", a, b);
+    }
+
+    #[test]
+    fn inside_triangle_is_kept() {
+        let triangle = [vertex(-0.5, -0.5, 0., 1.), vertex(0.5, -0.5, 0.2, 1.), vertex(0., 2., -1., 2.)];
+        let polygon = clip_triangle(triangle);
+        assert_eq!(polygon.vertices().len(), 3);
+        for (v, original) in polygon.vertices().iter().zip(triangle.iter()) {
+            assert_eq!(v.coords, original.coords);
+        }
+        assert_eq!(polygon.edges(), &[true, true, true]);
+    }
+
+    #[test]
+    fn outside_triangle_is_dropped() {
+        let left = [vertex(-3., 0., 0., 1.), vertex(-2., 0., 0., 1.), vertex(-2., 1., 0., 1.)];
+        assert!(clip_triangle(left).vertices().is_empty());
+        // Behind the camera, `w` is negative.
+        let behind = [vertex(0., 0., 0., -1.), vertex(0.5, 0., 0., -1.), vertex(0., 0.5, 0., -1.)];
+        assert!(clip_triangle(behind).vertices().is_empty());
+    }
+
+    #[test]
+    fn near_plane_cuts_a_corner() {
+        // The last vertex is nearer than the near plane `z = w`.
+        let triangle = [vertex(-0.5, -0.5, 0., 1.), vertex(0.5, -0.5, 0., 1.), vertex(0., 0.5, 3., 1.)];
+        let polygon = clip_triangle(triangle);
+        assert_eq!(polygon.vertices().len(), 4);
+        for v in polygon.vertices() {
+            assert!(is_inside(v), "{:?} is outside", v.coords);
+            assert!(v.coords.z <= v.coords.w);
+            assert_near(v.varyings, v.coords);
+        }
+        // The corner makes way for an edge along the plane.
+        let on_plane: Vec<_> = polygon.vertices().iter().map(|v| v.coords.z == v.coords.w).collect();
+        assert_eq!(on_plane, [false, false, true, true]);
+        assert_eq!(polygon.edges(), &[true, true, false, true]);
+    }
+
+    #[test]
+    fn frustum_cuts_every_side() {
+        let triangle = [vertex(-2., -2., 0., 1.), vertex(2., -2., 0., 1.), vertex(0., 2., 0., 1.)];
+        let polygon = clip_triangle(triangle);
+        assert!(polygon.vertices().len() > 3);
+        for v in polygon.vertices() {
+            assert!(is_inside(v), "{:?} is outside", v.coords);
+            assert_near(v.varyings, v.coords);
+        }
+        // Only the parts of the slanted edges inside the view are left.
+        assert_eq!(polygon.edges().iter().filter(|edge| **edge).count(), 2);
+    }
+
+    #[test]
+    fn lines_are_cut_at_the_planes() {
+        let line = clip_line([vertex(-2., 0., 0., 1.), vertex(0.5, 0., 0., 1.)]).unwrap();
+        assert_near(line[0].coords, v4::new(-1., 0., 0., 1.));
+        assert_near(line[0].varyings, line[0].coords);
+        assert_near(line[1].coords, v4::new(0.5, 0., 0., 1.));
+        assert!(clip_line([vertex(-3., 0., 0., 1.), vertex(-2., 5., 0., 1.)]).is_none());
+        assert!(clip_line([vertex(0., 0., 2., 1.), vertex(0.5, 0., 3., 1.)]).is_none());
+    }
+}
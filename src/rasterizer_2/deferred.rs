@@ -0,0 +1,63 @@
+use nalgebra as na;
+use na::Vector3 as v3;
+use crate::julia::LightingShader;
+
+/// What a geometry pass stores for every sample, one field per render
+/// target.
+#[derive(Clone, Copy, Debug)]
+pub struct GSample {
+    pub albedo      : (u8, u8, u8, u8),
+    /// Unit normal of the surface, in world space.
+    pub normal      : v3<f32>,
+    pub position    : v3<f32>,
+    /// Material `0` marks the background: cleared samples have it and the
+    /// lighting pass skips them.
+    pub material    : u32,
+}
+
+impl Default for GSample {
+    fn default() -> Self {
+        GSample {
+            albedo      : (0, 0, 0, 0),
+            normal      : v3::new(0., 0., 0.),
+            position    : v3::new(0., 0., 0.),
+            material    : 0,
+        }
+    }
+}
+
+/// Light shining from a point in every direction, fading out to nothing at
+/// `radius`.
+#[derive(Clone, Copy, Debug)]
+pub struct PointLight {
+    pub position    : v3<f32>,
+    /// Intensity of every channel, `1.` lights the albedo as is.
+    pub color       : v3<f32>,
+    pub radius      : f32,
+}
+
+/// Lambertian lighting of the G-buffer by point lights.
+pub struct PointLightShader<'a> {
+    pub lights      : &'a [PointLight],
+    /// Light reaching every surface whatever its position.
+    pub ambient     : v3<f32>,
+}
+
+impl<'a> LightingShader for PointLightShader<'a> {
+    fn shade(&self, sample: &GSample) -> Option<(u8, u8, u8, u8)> {
+        let mut light = self.ambient;
+        for point in self.lights {
+            let to_light = point.position - sample.position;
+            let distance = to_light.norm();
+            if distance >= point.radius || distance == 0. {
+                continue;
+            }
+            let falloff = 1. - distance / point.radius;
+            let diffuse = sample.normal.dot(&to_light).max(0.) / distance;
+            light += point.color * (diffuse * falloff * falloff);
+        }
+        let channel = |albedo: u8, light: f32| (albedo as f32 * light).round().clamp(0., 255.) as u8;
+        let (r, g, b, a) = sample.albedo;
+        Some((channel(r, light.x), channel(g, light.y), channel(b, light.z), a))
+    }
+}
@@ -0,0 +1,55 @@
+use super::PrimitiveTopology;
+use std::fmt;
+
+/// Why a draw call was rejected. Draw calls are checked before anything is
+/// drawn, so a rejected draw call leaves the buffers untouched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderError {
+    /// An index refers past the end of the vertex buffer.
+    IndexOutOfRange { index: usize, vertex_count: usize },
+    /// The number of indices doesn't make a whole number of primitives.
+    IndexCountMismatch { index_count: usize, topology: PrimitiveTopology },
+    /// A triangle of a list uses the same vertex twice, so it has no area
+    /// whatever the transform. Strips and fans may repeat vertices, to
+    /// stitch them together. Triangles whose distinct vertices end up on a
+    /// line in the window are not errors: they are culled, and counted in
+    /// `FrameStats::triangles_culled`.
+    DegenerateTriangle { triangle: usize },
+    /// A vertex has a NaN or an infinite attribute, or the vertex shader
+    /// gave it a position that isn't finite.
+    NonFiniteVertex { index: usize },
+    /// The model matrix has a NaN or an infinite element.
+    NonFiniteTransform,
+    /// Points have to be a positive finite number of pixels wide.
+    InvalidPointSize { size: f32 },
+    /// An instance refers past the end of the textures of the draw.
+    TextureIndexOutOfRange { instance: usize, texture: usize, texture_count: usize },
+    /// The fragment shader writes a G-buffer, or the lighting pass reads
+    /// one, but the framebuffer has none.
+    MissingGBuffer,
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderError::IndexOutOfRange { index, vertex_count } =>
+                write!(f, "index {} is out of range for {} vertices", index, vertex_count),
+            RenderError::IndexCountMismatch { index_count, topology } =>
+                write!(f, "{} indices don't make whole primitives of {:?}", index_count, topology),
+            RenderError::DegenerateTriangle { triangle } =>
+                write!(f, "triangle {} uses a vertex twice", triangle),
+            RenderError::NonFiniteVertex { index } =>
+                write!(f, "vertex {} is not finite", index),
+            RenderError::NonFiniteTransform =>
+                write!(f, "model matrix is not finite"),
+            RenderError::InvalidPointSize { size } =>
+                write!(f, "point size {} is not positive", size),
+            RenderError::TextureIndexOutOfRange { instance, texture, texture_count } =>
+                write!(f, "instance {} uses texture {} out of {}", instance, texture, texture_count),
+            RenderError::MissingGBuffer =>
+                write!(f, "framebuffer has no G-buffer"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
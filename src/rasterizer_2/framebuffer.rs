@@ -0,0 +1,232 @@
+use nalgebra as na;
+use na::Vector2 as v2;
+use crate::texture::Texture;
+use super::blend::BlendState;
+use super::deferred::GSample;
+use super::hiz::HiZ;
+use super::multisample::SamplePattern;
+use super::state::{DepthState, StencilState};
+
+/// Color, depth and stencil attachments draws write to, and optionally a
+/// G-buffer. The depth attachment comes with its hierarchical depth buffer.
+///
+/// The color attachment is a `Texture`, so what was drawn into an offscreen
+/// framebuffer can be sampled by later draws. Row `y` of the texture is row
+/// `y` of the framebuffer, counted from the bottom.
+pub struct Framebuffer {
+    /// Resolved color of every pixel.
+    color           : Texture,
+    /// Color of every sample. Left empty when there is a single sample per
+    /// pixel, draws then write straight into `color`.
+    color_buffer    : Vec<(u8, u8, u8, u8)>,
+    depth_buffer    : Vec<f32>,
+    stencil_buffer  : Vec<u8>,
+    /// Render targets of every sample for deferred shading. Left empty
+    /// unless enabled.
+    gbuffer         : Vec<GSample>,
+    hiz             : HiZ,
+    sample_pattern  : SamplePattern,
+    shape           : v2<i32>,
+}
+
+impl Framebuffer {
+    /// Single-sampled framebuffer, cleared with the default depth and
+    /// stencil states.
+    pub fn new(width: i32, height: i32) -> Self {
+        let buff_sz = (width * height) as usize;
+        let shape = v2::new(width, height);
+        Self {
+            color           : Texture::blank(width as u32, height as u32),
+            color_buffer    : vec![],
+            depth_buffer    : vec![DepthState::default().clear_depth; buff_sz],
+            stencil_buffer  : vec![StencilState::default().clear_stencil; buff_sz],
+            gbuffer         : vec![],
+            hiz             : HiZ::new(shape, DepthState::default().clear_depth),
+            sample_pattern  : SamplePattern::single(),
+            shape,
+        }
+    }
+
+    pub fn shape(&self) -> v2<i32> { self.shape }
+
+    pub fn sample_pattern(&self) -> &SamplePattern { &self.sample_pattern }
+
+    /// Sets where pixels get sampled, see `Julia3D::set_sample_pattern`. The
+    /// attachments are reallocated and cleared.
+    pub(super) fn set_sample_pattern(&mut self, sample_pattern: SamplePattern, clear_depth: f32, clear_stencil: u8) {
+        let pixels = (self.shape.x * self.shape.y) as usize;
+        let samples = sample_pattern.samples();
+        self.color = Texture::blank(self.shape.x as u32, self.shape.y as u32);
+        self.color_buffer = if samples > 1 { vec![(0_u8, 0_u8, 0_u8, 0_u8); pixels * samples] } else { vec![] };
+        self.depth_buffer = vec![clear_depth; pixels * samples];
+        self.stencil_buffer = vec![clear_stencil; pixels * samples];
+        if !self.gbuffer.is_empty() {
+            self.gbuffer = vec![GSample::default(); pixels * samples];
+        }
+        self.hiz.clear(clear_depth);
+        self.sample_pattern = sample_pattern;
+    }
+
+    /// Attaches a cleared G-buffer, or frees it.
+    pub(super) fn set_gbuffer(&mut self, enabled: bool) {
+        self.gbuffer = if enabled { vec![GSample::default(); self.depth_buffer.len()] } else { vec![] };
+    }
+
+    /// The color attachment. With more than one sample per pixel, it only
+    /// gets updated by `resolve`.
+    ///
+    /// Its first row is the bottom one, where viewports with the default
+    /// `Origin::BottomLeft` put `y = -1`. Textures loaded from bitmaps start
+    /// from the bottom too, so a render sampled by later draws shows the same
+    /// way up as a bitmap would. Drawing with `Origin::TopLeft` flips it.
+    pub fn color(&self) -> &Texture { &self.color }
+
+    /// Depth of every sample, row by row with the samples of a pixel next to
+    /// each other.
+    pub fn depth(&self) -> &[f32] { &self.depth_buffer }
+
+    /// Stencil value of every sample, laid out like `depth`.
+    pub fn stencil(&self) -> &[u8] { &self.stencil_buffer }
+
+    /// Depth range of the blocks and the tiles of the depth attachment.
+    pub(super) fn hiz(&self) -> &HiZ { &self.hiz }
+
+    /// Render targets of every sample, laid out like `depth`. Empty unless
+    /// the G-buffer is enabled.
+    pub fn gbuffer(&self) -> &[GSample] { &self.gbuffer }
+
+    /// Color, depth and stencil of every sample, for the rasterizer.
+    pub(super) fn buffers_mut(&mut self) -> Attachments<'_, (u8, u8, u8, u8)> {
+        let color_buffer = if self.sample_pattern.samples() > 1 {
+            &mut self.color_buffer[..]
+        } else {
+            self.color.pixels_mut()
+        };
+        Attachments {
+            sample_pattern  : &self.sample_pattern,
+            color           : color_buffer,
+            depth           : &mut self.depth_buffer,
+            stencil         : &mut self.stencil_buffer,
+            hiz             : &mut self.hiz,
+        }
+    }
+
+    /// Color of every sample next to the G-buffer, for the lighting pass.
+    pub(super) fn lighting_buffers_mut(&mut self) -> Option<LightingBuffers<'_>> {
+        if self.gbuffer.is_empty() {
+            return None;
+        }
+        let color_buffer = if self.sample_pattern.samples() > 1 {
+            &mut self.color_buffer[..]
+        } else {
+            self.color.pixels_mut()
+        };
+        Some((color_buffer, &self.gbuffer))
+    }
+
+    pub(super) fn clear(&mut self, clear_depth: f32, clear_stencil: u8) {
+        for sample in self.gbuffer.iter_mut() {
+            *sample = GSample::default();
+        }
+        let attachments = self.buffers_mut();
+        for color in attachments.color.iter_mut() {
+            *color = (0_u8, 0_u8, 0_u8, 0_u8);
+        }
+        for depth in attachments.depth.iter_mut() {
+            *depth = clear_depth;
+        }
+        for stencil in attachments.stencil.iter_mut() {
+            *stencil = clear_stencil;
+        }
+        attachments.hiz.clear(clear_depth);
+    }
+
+    /// Averages the samples of every pixel into the color attachment. Does
+    /// nothing with a single sample per pixel.
+    pub fn resolve(&mut self) {
+        let samples = self.sample_pattern.samples();
+        if samples == 1 {
+            return;
+        }
+        for (pixel, color) in self.color_buffer.chunks(samples).zip(self.color.pixels_mut().iter_mut()) {
+            let (mut r, mut g, mut b, mut a) = (0_u32, 0_u32, 0_u32, 0_u32);
+            for sample in pixel {
+                r += sample.0 as u32;
+                g += sample.1 as u32;
+                b += sample.2 as u32;
+                a += sample.3 as u32;
+            }
+            let n = samples as u32;
+            *color = (
+                ((r + n / 2) / n) as u8,
+                ((g + n / 2) / n) as u8,
+                ((b + n / 2) / n) as u8,
+                ((a + n / 2) / n) as u8);
+        }
+    }
+}
+
+/// Buffers of a framebuffer a draw writes to, `C` being what its fragment
+/// shader outputs for a sample. Only the crate sees them, as the rasterizer
+/// keeps `hiz` up to date with `depth`.
+pub struct Attachments<'a, C> {
+    pub(crate) sample_pattern   : &'a SamplePattern,
+    pub(crate) color            : &'a mut [C],
+    pub(crate) depth            : &'a mut [f32],
+    pub(crate) stencil          : &'a mut [u8],
+    pub(crate) hiz              : &'a mut HiZ,
+}
+
+/// Color of every sample the lighting pass blends into, and the G-buffer it
+/// reads.
+pub type LightingBuffers<'a> = (&'a mut [(u8, u8, u8, u8)], &'a [GSample]);
+
+/// What fragment shaders write for a sample: a color, or a `GSample` with
+/// every render target of the G-buffer. Sealed, as the buffers it gives the
+/// rasterizer have to stay out of reach of callers.
+pub trait RenderTarget: Copy + Default + Send + Sync + Sealed {
+    /// Combines `self` with the value `dst` already stored.
+    fn blend(self, dst: Self, blend_state: &BlendState) -> Self;
+}
+
+/// The part of `RenderTarget` only the crate sees.
+pub trait Sealed: Sized {
+    /// Where the framebuffer keeps the value of every sample, along with its
+    /// other buffers. `None` when the framebuffer has no such buffer.
+    fn buffers(framebuffer: &mut Framebuffer) -> Option<Attachments<'_, Self>>;
+}
+
+impl Sealed for (u8, u8, u8, u8) {
+    fn buffers(framebuffer: &mut Framebuffer) -> Option<Attachments<'_, Self>> {
+        Some(framebuffer.buffers_mut())
+    }
+}
+
+impl RenderTarget for (u8, u8, u8, u8) {
+    fn blend(self, dst: Self, blend_state: &BlendState) -> Self {
+        blend_state.blend(self, dst)
+    }
+}
+
+impl Sealed for GSample {
+    fn buffers(framebuffer: &mut Framebuffer) -> Option<Attachments<'_, Self>> {
+        if framebuffer.gbuffer.is_empty() {
+            return None;
+        }
+        Some(Attachments {
+            sample_pattern  : &framebuffer.sample_pattern,
+            color           : &mut framebuffer.gbuffer,
+            depth           : &mut framebuffer.depth_buffer,
+            stencil         : &mut framebuffer.stencil_buffer,
+            hiz             : &mut framebuffer.hiz,
+        })
+    }
+}
+
+/// Render targets of the G-buffer are written as is, whatever the blend
+/// state.
+impl RenderTarget for GSample {
+    fn blend(self, _: Self, _: &BlendState) -> Self {
+        self
+    }
+}
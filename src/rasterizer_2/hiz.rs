@@ -0,0 +1,159 @@
+use nalgebra as na;
+use na::Vector2 as v2;
+use super::tiles::TILE_SIZE;
+
+/// Side of the square blocks of pixels the hierarchical depth buffer keeps
+/// the depth range of. Even, so that no quad straddles two blocks.
+pub const BLOCK_SIZE: i32 = 8;
+const BLOCKS_PER_TILE: i32 = TILE_SIZE / BLOCK_SIZE;
+
+/// Inclusive range of depths.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthRange {
+    pub min         : f32,
+    pub max         : f32,
+}
+
+impl DepthRange {
+    pub fn new(depth: f32) -> Self {
+        DepthRange { min: depth, max: depth }
+    }
+
+    /// Range containing no depth, to grow with `include`.
+    pub fn empty() -> Self {
+        DepthRange { min: f32::INFINITY, max: f32::NEG_INFINITY }
+    }
+
+    pub fn include(&mut self, depth: f32) {
+        self.min = self.min.min(depth);
+        self.max = self.max.max(depth);
+    }
+
+    fn union(&mut self, other: DepthRange) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+}
+
+/// Hierarchical depth buffer: the range of the depths of every 8×8 block of
+/// pixels, and of every tile, over all their samples. Whatever gets drawn
+/// there has to pass the depth test against some depth of the range, so
+/// primitives and bounding boxes failing against the whole range can be
+/// skipped without looking at the depth buffer.
+///
+/// There are only these two levels, on purpose. Tiles are what primitives
+/// are binned into, so a tile range is the coarsest test that can skip a
+/// primitive, and a range per 8×8 block is fine enough to follow the outline
+/// of occluders while staying cheap to recompute after every tile. Levels
+/// above tiles would span tiles shaded by different threads, and levels in
+/// between would only repeat what the blocks of a tile already tell.
+///
+/// The ranges are laid out tile by tile, as the rasterizer works: the range
+/// of a tile comes first, then the ones of its blocks row by row.
+pub struct HiZ {
+    ranges          : Vec<DepthRange>,
+    tiles_x         : i32,
+}
+
+/// Ranges of a tile and of its blocks.
+pub const TILE_RANGES: usize = 1 + (BLOCKS_PER_TILE * BLOCKS_PER_TILE) as usize;
+
+impl HiZ {
+    pub fn new(shape: v2<i32>, clear_depth: f32) -> Self {
+        let tiles_x = (shape.x + TILE_SIZE - 1) / TILE_SIZE;
+        let tiles_y = (shape.y + TILE_SIZE - 1) / TILE_SIZE;
+        HiZ {
+            ranges          : vec![DepthRange::new(clear_depth); TILE_RANGES * (tiles_x * tiles_y) as usize],
+            tiles_x,
+        }
+    }
+
+    /// Sets every range to the depth the buffer was cleared with.
+    pub fn clear(&mut self, clear_depth: f32) {
+        for range in self.ranges.iter_mut() {
+            *range = DepthRange::new(clear_depth);
+        }
+    }
+
+    fn tile_index(&self, tile: v2<i32>) -> usize {
+        (tile.y * self.tiles_x + tile.x) as usize * TILE_RANGES
+    }
+
+    pub fn tile(&self, tile: v2<i32>) -> DepthRange {
+        self.ranges[self.tile_index(tile)]
+    }
+
+    /// Range of the block, in blocks from the bottom-left corner.
+    pub fn block(&self, block: v2<i32>) -> DepthRange {
+        let tile = self.tile_index(block / BLOCKS_PER_TILE);
+        self.ranges[tile + 1 + (block.y % BLOCKS_PER_TILE * BLOCKS_PER_TILE + block.x % BLOCKS_PER_TILE) as usize]
+    }
+
+    /// The `TILE_RANGES` ranges of every tile, in order, for the rasterizer
+    /// to update along with the depth buffer.
+    pub fn ranges_mut(&mut self) -> &mut [DepthRange] {
+        &mut self.ranges
+    }
+}
+
+/// Ranges of one tile of a `HiZ`.
+pub struct HiZTile<'a> {
+    /// Window coordinates of the bottom-left pixel of the tile.
+    origin          : v2<i32>,
+    /// Pixels of the tile inside the framebuffer along each axis.
+    size            : v2<i32>,
+    samples         : usize,
+    ranges          : &'a mut [DepthRange],
+}
+
+impl<'a> HiZTile<'a> {
+    pub fn new(ranges: &'a mut [DepthRange], origin: v2<i32>, size: v2<i32>, samples: usize) -> Self {
+        HiZTile { origin, size, samples, ranges }
+    }
+
+    pub fn tile(&self) -> DepthRange {
+        self.ranges[0]
+    }
+
+    /// Range of the block, in blocks from the bottom-left corner of the
+    /// window.
+    pub fn block(&self, block: v2<i32>) -> DepthRange {
+        self.ranges[self.block_index(block)]
+    }
+
+    fn block_index(&self, block: v2<i32>) -> usize {
+        let block = block - self.origin / BLOCK_SIZE;
+        debug_assert!(0 <= block.x && block.x < BLOCKS_PER_TILE && 0 <= block.y && block.y < BLOCKS_PER_TILE);
+        1 + (block.y * BLOCKS_PER_TILE + block.x) as usize
+    }
+
+    /// Recomputes from the rows of the depth buffer of the tile the ranges
+    /// of the blocks overlapping the inclusive pixel range `[min, max]`,
+    /// then the range of the tile.
+    pub fn update(&mut self, depth_rows: &[&mut [f32]], min: v2<i32>, max: v2<i32>) {
+        let top = self.origin + self.size - v2::new(1, 1);
+        for by in min.y / BLOCK_SIZE..=max.y / BLOCK_SIZE {
+            for bx in min.x / BLOCK_SIZE..=max.x / BLOCK_SIZE {
+                let mut range = DepthRange::empty();
+                let from = (bx * BLOCK_SIZE - self.origin.x) as usize * self.samples;
+                let to = (std::cmp::min(bx * BLOCK_SIZE + BLOCK_SIZE - 1, top.x) - self.origin.x + 1) as usize * self.samples;
+                for y in by * BLOCK_SIZE..=std::cmp::min(by * BLOCK_SIZE + BLOCK_SIZE - 1, top.y) {
+                    for depth in &depth_rows[(y - self.origin.y) as usize][from..to] {
+                        range.include(*depth);
+                    }
+                }
+                let index = self.block_index(v2::new(bx, by));
+                self.ranges[index] = range;
+            }
+        }
+
+        let mut range = DepthRange::empty();
+        let blocks = (self.size + v2::new(BLOCK_SIZE - 1, BLOCK_SIZE - 1)) / BLOCK_SIZE;
+        for by in 0..blocks.y {
+            for bx in 0..blocks.x {
+                range.union(self.ranges[1 + (by * BLOCKS_PER_TILE + bx) as usize]);
+            }
+        }
+        self.ranges[0] = range;
+    }
+}
@@ -0,0 +1,16 @@
+/// Integer type an index buffer can be made of.
+pub trait Index: Copy {
+    fn to_usize(self) -> usize;
+}
+
+impl Index for u16 {
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
+
+impl Index for u32 {
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
@@ -0,0 +1,66 @@
+use nalgebra as na;
+use na::Vector2 as v2;
+use super::utils;
+
+/// Largest number of samples per pixel, bounded by the width of coverage
+/// masks.
+pub const MAX_SAMPLES: usize = 32;
+
+/// Positions of the samples inside a pixel.
+///
+/// Offsets are measured from the bottom-left corner of the pixel in pixels,
+/// each coordinate in `[0, 1)`, and get snapped to the subpixel grid.
+#[derive(Clone, Debug)]
+pub struct SamplePattern {
+    offsets     : Vec<v2<i64>>,
+}
+
+impl SamplePattern {
+    /// One sample at the center of the pixel, that is no anti-aliasing.
+    pub fn single() -> Self {
+        Self::new(&[(0.5, 0.5)])
+    }
+
+    /// The usual rotated-grid patterns for 1, 2, 4 or 8 samples. `None` for
+    /// any other number of samples, which have no standard pattern.
+    pub fn standard(samples: usize) -> Option<Self> {
+        // In sixteenths of a pixel from its center.
+        let offsets: &[(i32, i32)] = match samples {
+            1 => &[(0, 0)],
+            2 => &[(4, 4), (-4, -4)],
+            4 => &[(-2, -6), (6, -2), (-6, 2), (2, 6)],
+            8 => &[(1, -3), (-1, 3), (5, 1), (-3, -5), (-5, 5), (-7, -1), (3, 7), (7, -7)],
+            _ => return None,
+        };
+        let offsets: Vec<(f32, f32)> = offsets.iter()
+            .map(|(x, y)| (0.5 + *x as f32 / 16., 0.5 + *y as f32 / 16.))
+            .collect();
+        Some(Self::new(&offsets))
+    }
+
+    pub fn new(offsets: &[(f32, f32)]) -> Self {
+        assert!(!offsets.is_empty() && offsets.len() <= MAX_SAMPLES,
+                "A pixel takes from 1 to {} samples", MAX_SAMPLES);
+        SamplePattern {
+            offsets: offsets.iter()
+                .map(|(x, y)| utils::snap(v2::new(*x, *y)))
+                .collect(),
+        }
+    }
+
+    pub fn samples(&self) -> usize { self.offsets.len() }
+
+    /// Fixed-point offsets of the samples from the pixel corner.
+    pub fn offsets(&self) -> &[v2<i64>] { &self.offsets }
+
+    /// Componentwise minimum and maximum of the fixed-point offsets.
+    pub fn bounds(&self) -> (v2<i64>, v2<i64>) {
+        let mut min = self.offsets[0];
+        let mut max = self.offsets[0];
+        for offset in self.offsets.iter() {
+            min = v2::new(min.x.min(offset.x), min.y.min(offset.y));
+            max = v2::new(max.x.max(offset.x), max.y.max(offset.y));
+        }
+        (min, max)
+    }
+}
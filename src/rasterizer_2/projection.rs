@@ -0,0 +1,102 @@
+use nalgebra as na;
+use na::Matrix4 as mat4;
+
+/// Maps view space, where the camera looks down the negative z axis, into
+/// clip space.
+///
+/// Depth goes to `1` on the near plane and to `-1` on the far plane, so
+/// fragments closer to the camera have greater depth.
+#[derive(Clone, Copy, Debug)]
+pub enum Projection {
+    Perspective {
+        /// Vertical field of view, in radians.
+        fov_y   : f32,
+        /// Width of the view divided by its height.
+        aspect  : f32,
+        near    : f32,
+        far     : f32,
+    },
+    Orthographic {
+        left    : f32,
+        right   : f32,
+        bottom  : f32,
+        top     : f32,
+        near    : f32,
+        far     : f32,
+    },
+}
+
+impl Projection {
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        Projection::Perspective { fov_y, aspect, near, far }
+    }
+
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        Projection::Orthographic { left, right, bottom, top, near, far }
+    }
+
+    pub fn matrix(&self) -> mat4<f32> {
+        match *self {
+            Projection::Perspective { fov_y, aspect, near, far } => {
+                let f = 1. / (fov_y / 2.).tan();
+                mat4::new(
+                    f / aspect, 0., 0.,                             0.,
+                    0.,         f,  0.,                             0.,
+                    0.,         0., (far + near) / (far - near),    2. * far * near / (far - near),
+                    0.,         0., -1.,                            0.,
+                )
+            },
+            Projection::Orthographic { left, right, bottom, top, near, far } => {
+                mat4::new(
+                    2. / (right - left),    0.,                     0.,                 -(right + left) / (right - left),
+                    0.,                     2. / (top - bottom),    0.,                 -(top + bottom) / (top - bottom),
+                    0.,                     0.,                     2. / (far - near),  (far + near) / (far - near),
+                    0.,                     0.,                     0.,                 1.,
+                )
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use na::Vector3 as v3;
+    use na::Vector4 as v4;
+
+    /// Normalized device coordinates of a view-space point.
+    fn ndc(projection: Projection, x: f32, y: f32, z: f32) -> v3<f32> {
+        let clip = projection.matrix() * v4::new(x, y, z, 1.);
+        clip.xyz() / clip.w
+    }
+
+    fn assert_near(a: v3<f32>, b: v3<f32>) {
+        assert!((a - b).norm() < 1e-5, "{:?} isn't {:?}", a, b);
+    }
+
+    #[test]
+    fn perspective_maps_the_frustum_with_reversed_depth() {
+        let (near, far) = (0.5, 20.);
+        let projection = Projection::perspective(std::f32::consts::FRAC_PI_2, 2., near, far);
+        // With a field of view of 90°, the top of the view is as far up as
+        // the point is away, and the side twice as far across.
+        assert_near(ndc(projection, 0., 0., -near), v3::new(0., 0., 1.));
+        assert_near(ndc(projection, 2. * far, far, -far), v3::new(1., 1., -1.));
+        assert_near(ndc(projection, -6., -3., -3.), v3::new(-1., -1., ndc(projection, 0., 0., -3.).z));
+
+        let mut depth = 1.;
+        for k in 1..20 {
+            let z = ndc(projection, 0., 0., -near - k as f32).z;
+            assert!(z < depth && z > -1., "depth {} at {}", z, near + k as f32);
+            depth = z;
+        }
+    }
+
+    #[test]
+    fn orthographic_maps_the_box_with_reversed_depth() {
+        let projection = Projection::orthographic(-4., 2., -1., 3., 1., 9.);
+        assert_near(ndc(projection, -4., -1., -1.), v3::new(-1., -1., 1.));
+        assert_near(ndc(projection, 2., 3., -9.), v3::new(1., 1., -1.));
+        assert_near(ndc(projection, -1., 1., -5.), v3::new(0., 0., 0.));
+    }
+}
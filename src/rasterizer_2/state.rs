@@ -0,0 +1,278 @@
+use super::hiz::DepthRange;
+
+/// Which faces are discarded before rasterization.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+}
+
+/// Winding order, as seen on the screen, of the faces that look at the camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrontFace {
+    Cw,
+    Ccw,
+}
+
+/// How the triangles get drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PolygonMode {
+    /// Every covered pixel is shaded.
+    Fill,
+    /// Only the edges are drawn, depth tested against what is already there.
+    Line,
+    /// The edges are drawn only where the surface of the mesh is visible.
+    /// The surface itself hides what is behind it and stays blank.
+    HiddenLine,
+}
+
+/// How the depth of a fragment is compared with the depth already in the
+/// buffer. Nearer fragments have greater depth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareFunc {
+    Never,
+    Less,
+    LessEqual,
+    Equal,
+    Greater,
+    GreaterEqual,
+    NotEqual,
+    Always,
+}
+
+impl CompareFunc {
+    /// Whether `value` passes against `stored`.
+    pub fn compare<T: PartialOrd>(self, value: T, stored: T) -> bool {
+        match self {
+            CompareFunc::Never          => false,
+            CompareFunc::Less           => value < stored,
+            CompareFunc::LessEqual      => value <= stored,
+            CompareFunc::Equal          => value == stored,
+            CompareFunc::Greater        => value > stored,
+            CompareFunc::GreaterEqual   => value >= stored,
+            CompareFunc::NotEqual       => value != stored,
+            CompareFunc::Always         => true,
+        }
+    }
+
+    /// Whether `depth` passes against `stored`. `depth` is moved up to
+    /// `tolerance` in whichever direction helps it pass.
+    pub fn test(self, depth: f32, stored: f32, tolerance: f32) -> bool {
+        match self {
+            CompareFunc::Less | CompareFunc::LessEqual          => self.compare(depth - tolerance, stored),
+            CompareFunc::Greater | CompareFunc::GreaterEqual    => self.compare(depth + tolerance, stored),
+            CompareFunc::Equal                                  => (depth - stored).abs() <= tolerance,
+            CompareFunc::NotEqual                               => (depth - stored).abs() > tolerance,
+            _                                                   => self.compare(depth, stored),
+        }
+    }
+
+    /// Whether every depth in `depths` is sure to fail against every depth in
+    /// `stored`. Depths get rounded on the way, so ranges that merely touch
+    /// are never rejected.
+    pub fn rejects(self, depths: DepthRange, stored: DepthRange) -> bool {
+        let margin = |a: f32, b: f32| (a.abs() + b.abs() + 1.) * 1e-5;
+        let below = depths.max < stored.min - margin(depths.max, stored.min);
+        let above = depths.min > stored.max + margin(depths.min, stored.max);
+        match self {
+            CompareFunc::Never                                  => true,
+            CompareFunc::Less | CompareFunc::LessEqual          => above,
+            CompareFunc::Greater | CompareFunc::GreaterEqual    => below,
+            CompareFunc::Equal                                  => below || above,
+            _                                                   => false,
+        }
+    }
+}
+
+/// How fragments are tested against and written to the depth buffer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthState {
+    pub compare         : CompareFunc,
+    /// Whether fragments that pass store their depth.
+    pub write_enabled   : bool,
+    /// Depth the buffer is filled with by `clear`.
+    pub clear_depth     : f32,
+    pub bias            : DepthBias,
+}
+
+impl Default for DepthState {
+    fn default() -> Self {
+        Self {
+            compare         : CompareFunc::Greater,
+            write_enabled   : true,
+            clear_depth     : -1000.,
+            bias            : DepthBias::default(),
+        }
+    }
+}
+
+/// Offset added to the depth of every fragment of a primitive before the
+/// depth test, to keep coplanar geometry from fighting. Positive offsets
+/// move fragments towards the camera.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DepthBias {
+    pub constant        : f32,
+    /// Scales the steepest change of depth from one pixel to the next.
+    pub slope_scale     : f32,
+}
+
+impl DepthBias {
+    pub fn offset(&self, max_slope: f32) -> f32 {
+        self.constant + self.slope_scale * max_slope
+    }
+}
+
+/// What happens to the stencil value of a sample after the tests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    Replace,
+    IncrementClamp,
+    DecrementClamp,
+    IncrementWrap,
+    DecrementWrap,
+    Invert,
+}
+
+impl StencilOp {
+    pub fn apply(self, value: u8, reference: u8) -> u8 {
+        match self {
+            StencilOp::Keep             => value,
+            StencilOp::Zero             => 0,
+            StencilOp::Replace          => reference,
+            StencilOp::IncrementClamp   => value.saturating_add(1),
+            StencilOp::DecrementClamp   => value.saturating_sub(1),
+            StencilOp::IncrementWrap    => value.wrapping_add(1),
+            StencilOp::DecrementWrap    => value.wrapping_sub(1),
+            StencilOp::Invert           => !value,
+        }
+    }
+}
+
+/// How fragments are tested against and written to the stencil buffer. The
+/// stencil test runs before the depth test and compares
+/// `reference & read_mask` with the stored value masked the same way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StencilState {
+    pub enabled         : bool,
+    pub compare         : CompareFunc,
+    pub reference       : u8,
+    pub read_mask       : u8,
+    /// Bits of the stored value the operations are allowed to change.
+    pub write_mask      : u8,
+    /// Applied when the stencil test fails.
+    pub fail_op         : StencilOp,
+    /// Applied when the stencil test passes but the depth test fails.
+    pub depth_fail_op   : StencilOp,
+    /// Applied when both tests pass.
+    pub pass_op         : StencilOp,
+    /// Value the buffer is filled with by `clear`.
+    pub clear_stencil   : u8,
+}
+
+impl Default for StencilState {
+    fn default() -> Self {
+        Self {
+            enabled         : false,
+            compare         : CompareFunc::Always,
+            reference       : 0,
+            read_mask       : 0xff,
+            write_mask      : 0xff,
+            fail_op         : StencilOp::Keep,
+            depth_fail_op   : StencilOp::Keep,
+            pass_op         : StencilOp::Keep,
+            clear_stencil   : 0,
+        }
+    }
+}
+
+impl StencilState {
+    /// Whether samples failing the stencil or the depth test leave the
+    /// stencil buffer as is, so that skipping them changes nothing.
+    pub fn keeps_failed(&self) -> bool {
+        !self.enabled || (self.fail_op == StencilOp::Keep && self.depth_fail_op == StencilOp::Keep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_test_with_tolerance() {
+        let table = [
+            (CompareFunc::Never,        1.,     0.,     0.,     false),
+            (CompareFunc::Always,       0.,     1.,     0.,     true),
+            (CompareFunc::Greater,      2.,     1.,     0.,     true),
+            (CompareFunc::Greater,      1.,     1.,     0.,     false),
+            (CompareFunc::Greater,      1.,     1.,     0.01,   true),
+            (CompareFunc::Greater,      0.5,    1.,     0.01,   false),
+            (CompareFunc::GreaterEqual, 1.,     1.,     0.,     true),
+            (CompareFunc::GreaterEqual, 0.98,   1.,     0.01,   false),
+            (CompareFunc::Less,         0.,     1.,     0.,     true),
+            (CompareFunc::Less,         1.,     1.,     0.,     false),
+            (CompareFunc::Less,         1.,     1.,     0.01,   true),
+            (CompareFunc::Less,         2.,     1.,     0.01,   false),
+            (CompareFunc::LessEqual,    1.,     1.,     0.,     true),
+            (CompareFunc::LessEqual,    1.02,   1.,     0.01,   false),
+            (CompareFunc::Equal,        1.,     1.,     0.,     true),
+            (CompareFunc::Equal,        1.005,  1.,     0.01,   true),
+            (CompareFunc::Equal,        1.02,   1.,     0.01,   false),
+            (CompareFunc::NotEqual,     1.,     1.,     0.,     false),
+            (CompareFunc::NotEqual,     1.005,  1.,     0.01,   false),
+            (CompareFunc::NotEqual,     1.02,   1.,     0.01,   true),
+        ];
+        for &(compare, depth, stored, tolerance, pass) in table.iter() {
+            assert_eq!(compare.test(depth, stored, tolerance), pass,
+                "{:?} of {} against {} within {}", compare, depth, stored, tolerance);
+        }
+    }
+
+    #[test]
+    fn rejected_depth_ranges() {
+        let range = |min, max| DepthRange { min, max };
+        let stored = range(0., 1.);
+        let table = [
+            (CompareFunc::Never,        range(0.2, 0.8),    true),
+            (CompareFunc::Always,       range(-3., -2.),    false),
+            (CompareFunc::NotEqual,     range(0.5, 0.5),    false),
+            (CompareFunc::Greater,      range(-2., -1.),    true),
+            (CompareFunc::Greater,      range(-1., -1e-5),  false),
+            (CompareFunc::Greater,      range(-1., 0.),     false),
+            (CompareFunc::Greater,      range(0.5, 2.),     false),
+            (CompareFunc::GreaterEqual, range(-2., -1.),    true),
+            (CompareFunc::Less,         range(2., 3.),      true),
+            (CompareFunc::Less,         range(1.00001, 2.), false),
+            (CompareFunc::Less,         range(1., 2.),      false),
+            (CompareFunc::Less,         range(-1., 0.5),    false),
+            (CompareFunc::LessEqual,    range(2., 3.),      true),
+            (CompareFunc::Equal,        range(2., 3.),      true),
+            (CompareFunc::Equal,        range(-3., -2.),    true),
+            (CompareFunc::Equal,        range(0.5, 0.6),    false),
+        ];
+        for &(compare, depths, rejected) in table.iter() {
+            assert_eq!(compare.rejects(depths, stored), rejected, "{:?} of {:?}", compare, depths);
+        }
+    }
+
+    #[test]
+    fn stencil_ops_at_the_ends_of_the_range() {
+        let reference = 0x5a;
+        let table = [
+            (StencilOp::Keep,           0,      255),
+            (StencilOp::Zero,           0,      0),
+            (StencilOp::Replace,        0x5a,   0x5a),
+            (StencilOp::IncrementClamp, 1,      255),
+            (StencilOp::DecrementClamp, 0,      254),
+            (StencilOp::IncrementWrap,  1,      0),
+            (StencilOp::DecrementWrap,  255,    254),
+            (StencilOp::Invert,         255,    0),
+        ];
+        for &(op, from_zero, from_max) in table.iter() {
+            assert_eq!(op.apply(0, reference), from_zero, "{:?} of 0", op);
+            assert_eq!(op.apply(255, reference), from_max, "{:?} of 255", op);
+        }
+    }
+}
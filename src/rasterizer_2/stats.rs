@@ -0,0 +1,177 @@
+use std::time::{Duration, Instant};
+
+/// What the draws since the last `reset_frame_stats` cost, stage by stage.
+///
+/// Stages running on several threads add up the time of every thread, so
+/// they can take longer than the frame. Times are only measured while
+/// profiling is on, counts always.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameStats {
+    /// Running the vertex shaders.
+    pub vertex              : Duration,
+    /// Clipping, culling, mapping primitives onto the window and binning
+    /// them.
+    pub setup               : Duration,
+    /// Walking the pixels of the primitives.
+    pub raster              : Duration,
+    /// Interpolating the varyings and running the fragment shaders.
+    pub fragment            : Duration,
+    /// Depth and stencil tests, and writing what passes.
+    pub depth_test          : Duration,
+    /// Triangles assembled from the indices of the draws.
+    pub triangles_submitted : u64,
+    /// Triangles crossing the view frustum, cut or dropped by clipping.
+    pub triangles_clipped   : u64,
+    /// Triangles left after clipping that were discarded for facing away or
+    /// covering no pixel.
+    pub triangles_culled    : u64,
+    /// Pixels covered by a primitive, whether their fragment was discarded
+    /// or not.
+    pub fragments_generated : u64,
+    /// Fragments with at least one sample passing the depth and stencil
+    /// tests.
+    pub fragments_passed    : u64,
+    pub samples_passed      : u64,
+    /// Pixels of the framebuffer of the last draw.
+    pub pixels              : u64,
+}
+
+impl FrameStats {
+    /// How many times every pixel got drawn on average.
+    pub fn overdraw(&self) -> f64 {
+        if self.pixels == 0 {
+            return 0.;
+        }
+        self.fragments_passed as f64 / self.pixels as f64
+    }
+
+    /// Adds the times and the counts of a draw, or of a part of it.
+    pub fn merge(&mut self, other: &FrameStats) {
+        self.vertex += other.vertex;
+        self.setup += other.setup;
+        self.raster += other.raster;
+        self.fragment += other.fragment;
+        self.depth_test += other.depth_test;
+        self.triangles_submitted += other.triangles_submitted;
+        self.triangles_clipped += other.triangles_clipped;
+        self.triangles_culled += other.triangles_culled;
+        self.fragments_generated += other.fragments_generated;
+        self.fragments_passed += other.fragments_passed;
+        self.samples_passed += other.samples_passed;
+    }
+
+    /// Names of the columns of `to_csv`.
+    pub fn csv_header() -> String {
+        let names: Vec<_> = FrameStats::default().fields().iter().map(|(name, _)| *name).collect();
+        names.join(",")
+    }
+
+    /// One line of comma-separated values, times in milliseconds.
+    pub fn to_csv(&self) -> String {
+        let values: Vec<_> = self.fields().iter().map(|(_, value)| value.clone()).collect();
+        values.join(",")
+    }
+
+    /// JSON object on a single line, times in milliseconds.
+    pub fn to_json(&self) -> String {
+        let members: Vec<_> = self.fields().iter()
+            .map(|(name, value)| format!("\"{}\": {}", name, value))
+            .collect();
+        format!("{{{}}}", members.join(", "))
+    }
+
+    fn fields(&self) -> [(&'static str, String); 13] {
+        let ms = |time: Duration| format!("{:.3}", time.as_secs_f64() * 1e3);
+        [
+            ("vertex_ms",           ms(self.vertex)),
+            ("setup_ms",            ms(self.setup)),
+            ("raster_ms",           ms(self.raster)),
+            ("fragment_ms",         ms(self.fragment)),
+            ("depth_test_ms",       ms(self.depth_test)),
+            ("triangles_submitted", self.triangles_submitted.to_string()),
+            ("triangles_clipped",   self.triangles_clipped.to_string()),
+            ("triangles_culled",    self.triangles_culled.to_string()),
+            ("fragments_generated", self.fragments_generated.to_string()),
+            ("fragments_passed",    self.fragments_passed.to_string()),
+            ("samples_passed",      self.samples_passed.to_string()),
+            ("pixels",              self.pixels.to_string()),
+            ("overdraw",            format!("{:.3}", self.overdraw())),
+        ]
+    }
+}
+
+/// Adds up the time spent in a stage, or does nothing when profiling is
+/// off.
+#[derive(Clone, Copy)]
+pub struct Stopwatch(Option<Instant>);
+
+impl Stopwatch {
+    pub fn start(enabled: bool) -> Self {
+        Stopwatch(if enabled { Some(Instant::now()) } else { None })
+    }
+
+    /// Adds the time since the start, or the last lap, to `total`.
+    pub fn lap(&mut self, total: &mut Duration) {
+        if let Some(start) = self.0 {
+            let now = Instant::now();
+            *total += now - start;
+            self.0 = Some(now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> FrameStats {
+        FrameStats {
+            vertex              : Duration::from_micros(1500),
+            triangles_submitted : 12,
+            fragments_passed    : 300,
+            pixels              : 200,
+            ..FrameStats::default()
+        }
+    }
+
+    #[test]
+    fn csv_and_json() {
+        let header = FrameStats::csv_header();
+        let csv = stats().to_csv();
+        assert_eq!(header.split(',').count(), csv.split(',').count());
+        assert!(header.starts_with("vertex_ms,setup_ms,"));
+        assert!(csv.starts_with("1.500,0.000,"));
+        assert!(csv.ends_with(",200,1.500"));
+
+        let json = stats().to_json();
+        assert!(json.starts_with("{\"vertex_ms\": 1.500, "));
+        assert!(json.contains("\"triangles_submitted\": 12, "));
+        assert!(json.ends_with("\"overdraw\": 1.500}"));
+        assert!(!json.contains('\n'));
+    }
+
+    #[test]
+    fn merge_adds_times_and_counts() {
+        let mut total = stats();
+        total.merge(&stats());
+        assert_eq!(total.vertex, Duration::from_micros(3000));
+        assert_eq!(total.triangles_submitted, 24);
+        assert_eq!(total.fragments_passed, 600);
+        assert_eq!(total.overdraw(), 3.);
+        assert_eq!(FrameStats::default().overdraw(), 0.);
+    }
+
+    #[test]
+    fn stopwatch_only_runs_when_enabled() {
+        let mut total = Duration::default();
+        let mut stopwatch = Stopwatch::start(false);
+        std::thread::sleep(Duration::from_millis(2));
+        stopwatch.lap(&mut total);
+        assert_eq!(total, Duration::default());
+
+        let mut stopwatch = Stopwatch::start(true);
+        std::thread::sleep(Duration::from_millis(2));
+        stopwatch.lap(&mut total);
+        assert!(total >= Duration::from_millis(2));
+    }
+}
@@ -0,0 +1,83 @@
+use nalgebra as na;
+use na::Vector4 as v4;
+use na::Vector2 as v2;
+use na::Matrix4 as mat4;
+use super::IVertex;
+use crate::julia::{Derivatives, FragmentShader, Flat, InstancedVertexShader, VertexShader};
+use crate::texture::Texture;
+
+/// Shaders behind `render` and `draw`: vertices are transformed by a single
+/// matrix and fragments take their color from a texture.
+pub struct TexturedShader<'a> {
+    pub mvp         : mat4<f32>,
+    pub texture     : &'a Texture,
+}
+
+impl<'a> VertexShader for TexturedShader<'a> {
+    type Input = IVertex;
+    type Varyings = v2<f32>;
+
+    fn shade(&self, iv: &IVertex) -> (v4<f32>, v2<f32>) {
+        let coords = v4::new(iv.coords.x,
+                             iv.coords.y,
+                             iv.coords.z,
+                             1.);
+        (self.mvp * coords, iv.tex_coords)
+    }
+}
+
+impl<'a> FragmentShader for TexturedShader<'a> {
+    type Varyings = v2<f32>;
+
+    fn shade(&self, tex_coords: &v2<f32>, _: &Derivatives<v2<f32>>) -> Option<(u8, u8, u8, u8)> {
+        Some(self.texture.get_pixel(*tex_coords))
+    }
+}
+
+/// One copy of the mesh in `render_instanced`.
+#[derive(Clone, Copy, Debug)]
+pub struct Instance {
+    pub model       : mat4<f32>,
+    /// Multiplies the color of the texture, `(255, 255, 255, 255)` keeps it
+    /// as is.
+    pub tint        : (u8, u8, u8, u8),
+    /// Index of the texture of the instance in the textures of the draw.
+    pub texture     : usize,
+}
+
+/// Tex coords, tint and texture index.
+type InstancedVaryings = (v2<f32>, Flat<v4<f32>>, Flat<u32>);
+
+/// Shaders behind `render_instanced`: like `TexturedShader`, with the model
+/// matrix, the tint and the texture taken from the instance.
+pub struct InstancedShader<'a> {
+    pub view_projection : mat4<f32>,
+    pub textures        : &'a [&'a Texture],
+}
+
+impl<'a> InstancedVertexShader for InstancedShader<'a> {
+    type Input = IVertex;
+    type Instance = Instance;
+    type Varyings = InstancedVaryings;
+
+    fn shade(&self, iv: &IVertex, instance: &Instance) -> (v4<f32>, InstancedVaryings) {
+        let coords = v4::new(iv.coords.x,
+                             iv.coords.y,
+                             iv.coords.z,
+                             1.);
+        let (r, g, b, a) = instance.tint;
+        let tint = v4::new(r as f32, g as f32, b as f32, a as f32) / 255.;
+        (self.view_projection * (instance.model * coords), (iv.tex_coords, Flat(tint), Flat(instance.texture as u32)))
+    }
+}
+
+impl<'a> FragmentShader for InstancedShader<'a> {
+    type Varyings = InstancedVaryings;
+
+    fn shade(&self, varyings: &InstancedVaryings, _: &Derivatives<InstancedVaryings>) -> Option<(u8, u8, u8, u8)> {
+        let (tex_coords, Flat(tint), Flat(texture)) = *varyings;
+        let (r, g, b, a) = self.textures[texture as usize].get_pixel(tex_coords);
+        let channel = |c: u8, t: f32| (c as f32 * t).round() as u8;
+        Some((channel(r, tint.x), channel(g, tint.y), channel(b, tint.z), channel(a, tint.w)))
+    }
+}
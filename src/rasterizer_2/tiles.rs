@@ -0,0 +1,129 @@
+use nalgebra as na;
+use na::Vector2 as v2;
+use super::framebuffer::Attachments;
+use super::hiz::{DepthRange, TILE_RANGES};
+
+/// Side of the square screen tiles triangles are binned into.
+pub const TILE_SIZE: i32 = 64;
+
+/// Indices of the triangles of a draw, grouped by the screen tiles their
+/// bounding boxes overlap. Every bin keeps the submission order.
+pub struct Bins {
+    shape   : v2<i32>,
+    tiles   : v2<i32>,
+    bins    : Vec<Vec<usize>>,
+}
+
+impl Bins {
+    pub fn new(shape: v2<i32>) -> Self {
+        let tiles = v2::new(
+            (shape.x + TILE_SIZE - 1) / TILE_SIZE,
+            (shape.y + TILE_SIZE - 1) / TILE_SIZE);
+        Bins {
+            shape,
+            tiles,
+            bins: vec![vec![]; (tiles.x * tiles.y) as usize],
+        }
+    }
+
+    /// Fits the bins to a framebuffer of another shape. Bins never shrink, so
+    /// switching back and forth between framebuffers doesn't touch the heap
+    /// once they have grown.
+    pub fn reshape(&mut self, shape: v2<i32>) {
+        self.shape = shape;
+        self.tiles = v2::new(
+            (shape.x + TILE_SIZE - 1) / TILE_SIZE,
+            (shape.y + TILE_SIZE - 1) / TILE_SIZE);
+        let tiles = (self.tiles.x * self.tiles.y) as usize;
+        if self.bins.len() < tiles {
+            self.bins.resize(tiles, vec![]);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for bin in self.bins.iter_mut() {
+            bin.clear();
+        }
+    }
+
+    /// Adds the triangle to every tile overlapping the inclusive pixel range
+    /// `[min, max]`, which must lie inside the framebuffer.
+    pub fn insert(&mut self, triangle: usize, min: v2<i32>, max: v2<i32>) {
+        for ty in min.y / TILE_SIZE..=max.y / TILE_SIZE {
+            for tx in min.x / TILE_SIZE..=max.x / TILE_SIZE {
+                self.bins[(tx + ty * self.tiles.x) as usize].push(triangle);
+            }
+        }
+    }
+
+    pub fn bin(&self, tile: v2<i32>) -> &[usize] {
+        &self.bins[(tile.x + tile.y * self.tiles.x) as usize]
+    }
+
+    /// Inclusive range of pixels of the tile.
+    pub fn rect(&self, tile: v2<i32>) -> (v2<i32>, v2<i32>) {
+        let min = tile * TILE_SIZE;
+        let max = v2::new(
+            std::cmp::min(min.x + TILE_SIZE, self.shape.x) - 1,
+            std::cmp::min(min.y + TILE_SIZE, self.shape.y) - 1);
+        (min, max)
+    }
+}
+
+/// Rows of a buffer inside a tile, from the bottom one. Rows above the
+/// framebuffer are empty.
+pub type TileRows<'a, T> = [&'a mut [T]; TILE_SIZE as usize];
+
+/// Parts of the buffers of a framebuffer a tile owns: its rows of the color,
+/// depth and stencil buffers, and its depth ranges.
+pub struct TileBuffers<'a, C> {
+    pub color       : TileRows<'a, C>,
+    pub depth       : TileRows<'a, f32>,
+    pub stencil     : TileRows<'a, u8>,
+    pub hiz         : &'a mut [DepthRange],
+}
+
+impl<'a, C> TileBuffers<'a, C> {
+    /// Splits the attachments of a framebuffer `width` pixels wide into the
+    /// parts every tile owns, and passes them to `f` along with the tile,
+    /// band after band of tiles. Nothing gets allocated, so tiles can be
+    /// handed over to other threads for free.
+    pub fn split<F: FnMut(v2<i32>, TileBuffers<'a, C>)>(attachments: Attachments<'a, C>, width: i32, mut f: F) {
+        let Attachments { sample_pattern, color, depth, stencil, hiz } = attachments;
+        let row_len = width as usize * sample_pattern.samples();
+        let band_len = TILE_SIZE as usize * row_len;
+        let tiles_x = ((width + TILE_SIZE - 1) / TILE_SIZE) as usize;
+        let bands = color.chunks_mut(band_len)
+            .zip(depth.chunks_mut(band_len))
+            .zip(stencil.chunks_mut(band_len))
+            .zip(hiz.ranges_mut().chunks_mut(tiles_x * TILE_RANGES));
+        for (ty, (((color, depth), stencil), hiz)) in bands.enumerate() {
+            let (mut color, mut depth, mut stencil) = (rows(color, row_len), rows(depth, row_len), rows(stencil, row_len));
+            let columns = TILE_SIZE as usize * sample_pattern.samples();
+            for (tx, hiz) in hiz.chunks_mut(TILE_RANGES).enumerate() {
+                f(v2::new(tx as i32, ty as i32), TileBuffers {
+                    color       : take_columns(&mut color, columns),
+                    depth       : take_columns(&mut depth, columns),
+                    stencil     : take_columns(&mut stencil, columns),
+                    hiz,
+                });
+            }
+        }
+    }
+}
+
+/// Rows of a band of tiles.
+fn rows<T>(band: &mut [T], row_len: usize) -> TileRows<'_, T> {
+    let mut rows = band.chunks_mut(row_len);
+    std::array::from_fn(|_| rows.next().unwrap_or_default())
+}
+
+/// Splits the first `columns` elements off every row.
+fn take_columns<'a, T>(rows: &mut TileRows<'a, T>, columns: usize) -> TileRows<'a, T> {
+    std::array::from_fn(|r| {
+        let row = std::mem::take(&mut rows[r]);
+        let (taken, rest) = row.split_at_mut(std::cmp::min(columns, row.len()));
+        rows[r] = rest;
+        taken
+    })
+}
@@ -0,0 +1,18 @@
+/// How the indices of a draw are grouped into primitives.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PrimitiveTopology {
+    /// Every index is a square point, `size` pixels wide.
+    PointList { size: f32 },
+    /// Every two indices are a line.
+    LineList,
+    /// Every index makes a line with the previous one.
+    LineStrip,
+    /// Every three indices are a triangle.
+    TriangleList,
+    /// Every index makes a triangle with the previous two. Every other
+    /// triangle is flipped, so they all keep the winding of the first one,
+    /// and the oldest of the three vertices provokes.
+    TriangleStrip,
+    /// Every index makes a triangle with the previous one and the first one.
+    TriangleFan,
+}
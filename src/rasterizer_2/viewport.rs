@@ -0,0 +1,72 @@
+use nalgebra as na;
+use na::Vector2 as v2;
+
+/// Which corner of the framebuffer the first row of pixels belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Origin {
+    /// Rows go upwards, as OpenGL expects when the buffer is uploaded as a
+    /// texture.
+    BottomLeft,
+    /// Rows go downwards, as most image formats store them.
+    TopLeft,
+}
+
+/// Rectangle of the framebuffer that normalized device coordinates are
+/// mapped onto.
+///
+/// Pixel `(x, y)` covers the square `[x, x + 1) x [y, y + 1)` of window
+/// coordinates and is sampled at its center.
+#[derive(Clone, Copy, Debug)]
+pub struct Viewport {
+    pub x       : i32,
+    pub y       : i32,
+    pub width   : i32,
+    pub height  : i32,
+    pub origin  : Origin,
+}
+
+impl Viewport {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Viewport { x, y, width, height, origin: Origin::BottomLeft }
+    }
+
+    pub fn with_origin(self, origin: Origin) -> Self {
+        Viewport { origin, ..self }
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    /// Maps normalized device coordinates to window coordinates.
+    pub fn transform(&self, ndc: v2<f32>) -> v2<f32> {
+        let x = self.x as f32 + (ndc.x + 1.) * 0.5 * self.width as f32;
+        let y = match self.origin {
+            Origin::BottomLeft  => self.y as f32 + (ndc.y + 1.) * 0.5 * self.height as f32,
+            Origin::TopLeft     => self.y as f32 + (1. - ndc.y) * 0.5 * self.height as f32,
+        };
+        v2::new(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corners_of_the_view() {
+        let viewport = Viewport::new(10, 20, 300, 100);
+        assert_eq!(viewport.transform(v2::new(-1., -1.)), v2::new(10., 20.));
+        assert_eq!(viewport.transform(v2::new(1., 1.)), v2::new(310., 120.));
+        assert_eq!(viewport.transform(v2::new(0., 0.5)), v2::new(160., 95.));
+        assert_eq!(viewport.aspect(), 3.);
+    }
+
+    #[test]
+    fn top_left_origin_flips_rows() {
+        let viewport = Viewport::new(10, 20, 300, 100).with_origin(Origin::TopLeft);
+        assert_eq!(viewport.transform(v2::new(-1., 1.)), v2::new(10., 20.));
+        assert_eq!(viewport.transform(v2::new(1., -1.)), v2::new(310., 120.));
+        assert_eq!(viewport.transform(v2::new(0., 0.5)), v2::new(160., 45.));
+    }
+}
@@ -1,28 +1,54 @@
 use na::Vector2 as v2;
 use std::path::Path;
 
+/// RGBA image that draws can sample, stored row by row from the bottom, like
+/// the color attachment of a `Framebuffer`. Tex coords go from `-1` to `1`,
+/// `y` pointing up.
 pub struct Texture {
-    image   : bmp::Image,
+    pixels  : Vec<(u8, u8, u8, u8)>,
     shape   : v2<u32>,
 }
 
 impl Texture {
+    /// Loads a bitmap. Bitmaps have no alpha channel, so every pixel is
+    /// opaque.
     pub fn new(p: &Path) -> Self {
         let image = bmp::open(p).unwrap();
         let shape = v2::new(image.get_width(), image.get_height());
+        let mut pixels = Vec::with_capacity((shape.x * shape.y) as usize);
+        // The bitmap is read from its top row.
+        for y in (0..shape.y).rev() {
+            for x in 0..shape.x {
+                let pixel = image.get_pixel(x, y);
+                pixels.push((pixel.r, pixel.g, pixel.b, 255));
+            }
+        }
+        Self {
+            pixels,
+            shape,
+        }
+    }
+
+    /// Texture with every pixel transparent black.
+    pub fn blank(width: u32, height: u32) -> Self {
         Self {
-            image: image,
-            shape: shape,
+            pixels: vec![(0_u8, 0_u8, 0_u8, 0_u8); (width * height) as usize],
+            shape: v2::new(width, height),
         }
     }
 
-    pub fn get_pixel(&self, dot: v2<f32>) -> (u8, u8, u8) {
+    pub fn shape(&self) -> v2<u32> { self.shape }
+
+    pub fn pixels(&self) -> &[(u8, u8, u8, u8)] { &self.pixels }
+
+    pub fn pixels_mut(&mut self) -> &mut [(u8, u8, u8, u8)] { &mut self.pixels }
+
+    pub fn get_pixel(&self, dot: v2<f32>) -> (u8, u8, u8, u8) {
         let x = (((dot.x + 1.0) * self.shape.x as f32 / 10.).round() * 5.) as u32;
         let y = (((dot.y + 1.0) * self.shape.y as f32 / 10.).round() * 5.) as u32;
         let x = std::cmp::min(x, self.shape.x - 1);
         let y = std::cmp::min(y, self.shape.y - 1);
-        let pixel = self.image.get_pixel(x, y);
-        (pixel.r, pixel.g, pixel.b)
+        self.pixels[(x + y * self.shape.x) as usize]
     }
 
     pub fn get_pixel_bilinear(&self, dot: v2<f32>) -> (u8, u8, u8) {
@@ -32,7 +58,7 @@ impl Texture {
         let y = std::cmp::min(y, self.shape.y - 2);
         let (mut r_sum, mut g_sum, mut b_sum) = (0, 0, 0);
         for (xc, yc) in [(x, y) /*, (x, y + 1), (x + 1, y + 1), (x + 1, y)*/].iter() {
-            let bmp::Pixel { r, g, b } = self.image.get_pixel(*xc, *yc);
+            let (r, g, b, _) = self.pixels[(*xc + *yc * self.shape.x) as usize];
             r_sum += r;
             g_sum += g;
             b_sum += b;
@@ -0,0 +1,59 @@
+mod common;
+
+use nalgebra as na;
+use na::Vector3 as v3;
+use common::{checker, cube, CUBE_TRIANGLES};
+use rust_test::rasterizer_2::{IVertex, Julia3D, PrimitiveTopology};
+use rust_test::texture::Texture;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts the allocations of the whole test binary.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn frame(julia: &mut Julia3D, vertices: &[IVertex], indices: &[u16], texture: &Texture, t: f32) {
+    julia.clear();
+    for k in 0..3 {
+        let translation = na::geometry::Translation3::new(k as f32 - 1., 0., -4. - k as f32).to_homogeneous();
+        let rotation = na::geometry::Rotation3::from_axis_angle(&v3::x_axis(), t + k as f32).to_homogeneous();
+        julia.draw(vertices, indices, PrimitiveTopology::TriangleList, texture, translation * rotation).unwrap();
+    }
+}
+
+#[test]
+fn single_threaded_frames_dont_allocate() {
+    let (vertices, texture) = (cube(), checker((200, 80, 20, 255), (20, 90, 220, 255)));
+    let mut julia = Julia3D::new(300, 200);
+    julia.set_threads(1);
+    for t in 0..3 {
+        frame(&mut julia, &vertices, &CUBE_TRIANGLES, &texture, t as f32);
+    }
+
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    for t in 3..10 {
+        frame(&mut julia, &vertices, &CUBE_TRIANGLES, &texture, t as f32);
+    }
+    assert_eq!(ALLOCATIONS.load(Ordering::SeqCst) - before, 0);
+    assert!(julia.framebuffer().color().pixels().iter().any(|p| p.3 != 0));
+}
@@ -0,0 +1,103 @@
+//! Meshes, textures and renderers shared by the integration tests. Every test
+//! binary uses some of them only.
+#![allow(dead_code)]
+
+use nalgebra as na;
+use na::Matrix4 as mat4;
+use na::Vector2 as v2;
+use na::Vector3 as v3;
+use rust_test::rasterizer_2::{IVertex, Julia3D, PrimitiveTopology, Projection, RenderError};
+use rust_test::texture::Texture;
+
+pub type Color = (u8, u8, u8, u8);
+
+pub const WHITE: Color = (255, 255, 255, 255);
+pub const BLANK: Color = (0, 0, 0, 0);
+
+/// Corners of the cube from `-1` to `1`. Bits 0, 1 and 2 of the index of a
+/// corner are set for positive `x`, `y` and `z`, and its tex coords are its
+/// `x` and `y`.
+pub fn cube() -> Vec<IVertex> {
+    (0..8).map(|i| {
+        let corner = |bit: usize| if i & bit == 0 { -1. } else { 1. };
+        IVertex {
+            coords      : v3::new(corner(1), corner(2), corner(4)),
+            tex_coords  : v2::new(corner(1), corner(2)),
+        }
+    }).collect()
+}
+
+/// Faces of the `cube`, wound counter-clockwise seen from outside.
+pub const CUBE_TRIANGLES: [u16; 36] = [
+    0, 2, 1, 1, 2, 3,
+    4, 5, 6, 5, 7, 6,
+    0, 1, 4, 1, 5, 4,
+    2, 6, 3, 3, 6, 7,
+    0, 4, 2, 2, 4, 6,
+    1, 3, 5, 3, 7, 5,
+];
+
+/// Edges of the `cube`, as a line list.
+pub const CUBE_EDGES: [u16; 24] = [
+    0, 1, 2, 3, 4, 5, 6, 7,
+    0, 2, 1, 3, 4, 6, 5, 7,
+    0, 4, 1, 5, 2, 6, 3, 7,
+];
+
+/// 16×16 texture of 4×4 squares of `a` and `b`, `a` in the corners.
+pub fn checker(a: Color, b: Color) -> Texture {
+    let mut texture = Texture::blank(16, 16);
+    for (i, pixel) in texture.pixels_mut().iter_mut().enumerate() {
+        *pixel = if (i % 16 / 4 + i / 16 / 4) % 2 == 0 { a } else { b };
+    }
+    texture
+}
+
+/// Texture of a single pixel.
+pub fn solid(color: Color) -> Texture {
+    let mut texture = Texture::blank(1, 1);
+    texture.pixels_mut()[0] = color;
+    texture
+}
+
+/// Cleared renderer whose window coordinates map straight onto `[-1, 1]`,
+/// with the depth of a fragment equal to its `z`.
+pub fn renderer(width: i32, height: i32) -> Julia3D {
+    let mut julia = Julia3D::new(width, height);
+    julia.set_projection(Projection::orthographic(-1., 1., -1., 1., -1., 1.));
+    julia.clear();
+    julia
+}
+
+/// Corners of the rectangle from `min` to `max` at depth `z`, counter-clockwise
+/// from `min`, the tex coords going from `-1` to `1` across it.
+pub fn rect(min: (f32, f32), max: (f32, f32), z: f32) -> Vec<IVertex> {
+    [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)].iter()
+        .map(|&(u, v): &(f32, f32)| IVertex {
+            coords      : v3::new(
+                if u < 0. { min.0 } else { max.0 },
+                if v < 0. { min.1 } else { max.1 },
+                z),
+            tex_coords  : v2::new(u, v),
+        })
+        .collect()
+}
+
+/// Triangles of a `rect`.
+pub const RECT_TRIANGLES: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+/// Draws the `rect` from `min` to `max` at depth `z`.
+pub fn draw_rect(
+    julia       : &mut Julia3D,
+    min         : (f32, f32),
+    max         : (f32, f32),
+    z           : f32,
+    texture     : &Texture) -> Result<(), RenderError> {
+    julia.draw(&rect(min, max, z), &RECT_TRIANGLES, PrimitiveTopology::TriangleList, texture, mat4::identity())
+}
+
+/// Color of the pixel of the resolved framebuffer, counted from the
+/// bottom-left corner.
+pub fn pixel(julia: &Julia3D, x: i32, y: i32) -> Color {
+    julia.framebuffer().color().pixels()[(x + y * julia.framebuffer().shape().x) as usize]
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use nalgebra as na;
+use na::Matrix4 as mat4;
+use common::{pixel, rect, renderer, solid, BLANK, RECT_TRIANGLES, WHITE};
+use rust_test::rasterizer_2::{CullMode, FrontFace, Julia3D, PrimitiveTopology};
+
+const SIZE: i32 = 32;
+
+/// Draws a square wound counter-clockwise on the left half of the view and
+/// one wound clockwise on the right half, and tells which ones show.
+fn drawn(cull_mode: CullMode, front_face: FrontFace) -> (bool, bool) {
+    let mut julia = renderer(SIZE, SIZE);
+    julia.set_cull_mode(cull_mode);
+    julia.set_front_face(front_face);
+    let ccw = rect((-1., -1.), (0., 1.), 0.);
+    let cw = rect((0., -1.), (1., 1.), 0.);
+    let texture = solid(WHITE);
+    julia.draw(&ccw, &RECT_TRIANGLES, PrimitiveTopology::TriangleList, &texture, mat4::identity()).unwrap();
+    julia.draw(&cw, &[0_u16, 2, 1, 0, 3, 2], PrimitiveTopology::TriangleList, &texture, mat4::identity()).unwrap();
+    let culled = julia.frame_stats().triangles_culled;
+    let shows = |julia: &Julia3D, x| match pixel(julia, x, SIZE / 2) {
+        WHITE   => true,
+        BLANK   => false,
+        color   => panic!("pixel ({}, {}) is {:?}", x, SIZE / 2, color),
+    };
+    let shown = (shows(&julia, SIZE / 4), shows(&julia, SIZE * 3 / 4));
+    assert_eq!(culled, 2 * (!shown.0 as u64 + !shown.1 as u64));
+    shown
+}
+
+#[test]
+fn cull_modes_and_front_faces() {
+    let table = [
+        (CullMode::None,    FrontFace::Ccw,     (true, true)),
+        (CullMode::None,    FrontFace::Cw,      (true, true)),
+        (CullMode::Back,    FrontFace::Ccw,     (true, false)),
+        (CullMode::Back,    FrontFace::Cw,      (false, true)),
+        (CullMode::Front,   FrontFace::Ccw,     (false, true)),
+        (CullMode::Front,   FrontFace::Cw,      (true, false)),
+    ];
+    for &(cull_mode, front_face, expected) in table.iter() {
+        assert_eq!(drawn(cull_mode, front_face), expected, "{:?} with {:?} in front", cull_mode, front_face);
+    }
+}
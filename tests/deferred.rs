@@ -0,0 +1,87 @@
+mod common;
+
+use nalgebra as na;
+use na::Vector3 as v3;
+use na::Vector4 as v4;
+use common::{pixel, rect, renderer, Color, BLANK, RECT_TRIANGLES};
+use rust_test::julia::{Derivatives, FragmentShader, VertexShader};
+use rust_test::rasterizer_2::{
+    GSample, IVertex, Julia3D, PointLight, PointLightShader, PrimitiveTopology, RenderError,
+};
+
+const SIZE: i32 = 32;
+const ALBEDO: Color = (200, 100, 50, 255);
+
+/// Passes the position through, in clip space and as a varying.
+struct Positions;
+
+impl VertexShader for Positions {
+    type Input = IVertex;
+    type Varyings = v3<f32>;
+
+    fn shade(&self, vertex: &IVertex) -> (v4<f32>, v3<f32>) {
+        (vertex.coords.push(1.), vertex.coords)
+    }
+}
+
+/// Geometry pass of a flat surface facing the camera.
+struct Geometry;
+
+impl FragmentShader<GSample> for Geometry {
+    type Varyings = v3<f32>;
+
+    fn shade(&self, position: &v3<f32>, _: &Derivatives<v3<f32>>) -> Option<GSample> {
+        Some(GSample { albedo: ALBEDO, normal: v3::new(0., 0., 1.), position: *position, material: 1 })
+    }
+}
+
+/// Renderer with a G-buffer holding the left half of the window.
+fn geometry_pass() -> Julia3D {
+    let mut julia = renderer(SIZE, SIZE);
+    julia.set_gbuffer(true);
+    julia.clear();
+    let vertices = rect((-1., -1.), (0., 1.), 0.);
+    julia.draw_shaded(&Positions, &Geometry, &vertices, &RECT_TRIANGLES, PrimitiveTopology::TriangleList).unwrap();
+    julia
+}
+
+#[test]
+fn missing_gbuffer() {
+    let mut julia = renderer(SIZE, SIZE);
+    let vertices = rect((-1., -1.), (0., 1.), 0.);
+    assert_eq!(
+        julia.draw_shaded(&Positions, &Geometry, &vertices, &RECT_TRIANGLES, PrimitiveTopology::TriangleList),
+        Err(RenderError::MissingGBuffer));
+    let shader = PointLightShader { lights: &[], ambient: v3::new(1., 1., 1.) };
+    assert_eq!(julia.light(&shader), Err(RenderError::MissingGBuffer));
+    assert!(julia.framebuffer().color().pixels().iter().all(|p| *p == BLANK));
+}
+
+#[test]
+fn ambient_lights_the_surface_only() {
+    let mut julia = geometry_pass();
+    // The geometry pass leaves the colors alone.
+    assert!(julia.framebuffer().color().pixels().iter().all(|p| *p == BLANK));
+
+    julia.light(&PointLightShader { lights: &[], ambient: v3::new(0.5, 1., 0.) }).unwrap();
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let expected = if x < SIZE / 2 { (100, 100, 0, 255) } else { BLANK };
+            assert_eq!(pixel(&julia, x, y), expected, "pixel ({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn point_light_fades_with_distance_and_angle() {
+    let mut julia = geometry_pass();
+    let lights = [PointLight { position: v3::new(-0.5, 0., 0.5), color: v3::new(1., 1., 1.), radius: 2. }];
+    julia.light(&PointLightShader { lights: &lights, ambient: v3::new(0., 0., 0.) }).unwrap();
+
+    let below = pixel(&julia, SIZE / 4, SIZE / 2);
+    let side = pixel(&julia, 0, SIZE / 2);
+    let corner = pixel(&julia, 0, 0);
+    assert!(below.0 > side.0 && side.0 > corner.0, "{:?}, {:?}, {:?}", below, side, corner);
+    assert!(below.0 < ALBEDO.0);
+    assert_eq!(pixel(&julia, SIZE - 1, SIZE / 2), BLANK);
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use nalgebra as na;
+use na::Matrix4 as mat4;
+use na::Vector2 as v2;
+use na::Vector3 as v3;
+use common::{pixel, renderer, solid, Color, RECT_TRIANGLES};
+use rust_test::rasterizer_2::{DepthBias, DepthState, IVertex, Julia3D, PrimitiveTopology};
+
+const SIZE: i32 = 32;
+const RED: Color = (255, 0, 0, 255);
+const BLUE: Color = (0, 0, 255, 255);
+
+/// Rectangle from `min` to `max` on the plane `z = slope * x + z0`.
+fn plane(julia: &mut Julia3D, min: (f32, f32), max: (f32, f32), slope: f32, z0: f32, color: Color) {
+    let vertices: Vec<_> = [(min.0, min.1), (max.0, min.1), (max.0, max.1), (min.0, max.1)].iter()
+        .map(|&(x, y)| IVertex { coords: v3::new(x, y, slope * x + z0), tex_coords: v2::new(0., 0.) })
+        .collect();
+    julia.draw(&vertices, &RECT_TRIANGLES, PrimitiveTopology::TriangleList, &solid(color), mat4::identity()).unwrap();
+}
+
+/// Draws a red plane over the whole view, then a blue decal `behind` it with
+/// the given bias, and counts the pixels of the decal that show.
+fn decal(slope: f32, behind: f32, bias: DepthBias) -> usize {
+    let mut julia = renderer(SIZE, SIZE);
+    plane(&mut julia, (-1., -1.), (1., 1.), slope, 0., RED);
+    julia.set_depth_state(DepthState { bias, ..DepthState::default() });
+    plane(&mut julia, (-0.5, -0.5), (0.5, 0.5), slope, -behind, BLUE);
+    let mut shown = 0;
+    for y in 8..24 {
+        for x in 8..24 {
+            shown += (pixel(&julia, x, y) == BLUE) as usize;
+        }
+    }
+    shown
+}
+
+#[test]
+fn constant_bias_lifts_coplanar_decals() {
+    assert_eq!(decal(0., 0., DepthBias::default()), 0);
+    assert_eq!(decal(0., 0., DepthBias { constant: 1e-3, slope_scale: 0. }), 16 * 16);
+    assert_eq!(decal(0., 0., DepthBias { constant: -1e-3, slope_scale: 0. }), 0);
+    assert_eq!(decal(0., 0.01, DepthBias { constant: 1e-3, slope_scale: 0. }), 0);
+}
+
+#[test]
+fn slope_scaled_bias_follows_slanted_planes() {
+    // The depth changes by 1/16 from one pixel to the next, so a slope
+    // scale of 1 makes up for being 0.03 behind where the constant bias
+    // doesn't.
+    assert_eq!(decal(1., 0.03, DepthBias { constant: 0.01, slope_scale: 0. }), 0);
+    assert_eq!(decal(1., 0.03, DepthBias { constant: 0., slope_scale: 1. }), 16 * 16);
+    // Flat planes get no slope bias.
+    assert_eq!(decal(0., 0.03, DepthBias { constant: 0., slope_scale: 1. }), 0);
+}
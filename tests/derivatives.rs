@@ -0,0 +1,75 @@
+use nalgebra as na;
+use na::Vector2 as v2;
+use na::Vector3 as v3;
+use na::Vector4 as v4;
+use rust_test::julia::{Derivatives, FragmentShader, VertexShader};
+use rust_test::rasterizer_2::{Julia3D, PrimitiveTopology};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+const WIDTH: i32 = 97;
+const HEIGHT: i32 = 61;
+
+/// `x` of the position alone, and `x` and `y` together.
+type Coords = (f32, v2<f32>);
+
+/// Vertices are given in clip space and carry their `Coords`.
+struct ClipSpace;
+
+impl VertexShader for ClipSpace {
+    type Input = v3<f32>;
+    type Varyings = Coords;
+
+    fn shade(&self, position: &v3<f32>) -> (v4<f32>, Coords) {
+        (v4::new(position.x, position.y, position.z, 1.), (position.x, position.xy()))
+    }
+}
+
+/// Keeps the varyings and the derivatives of every fragment.
+struct Recorder {
+    fragments   : Mutex<Vec<(Coords, Derivatives<Coords>)>>,
+}
+
+impl FragmentShader for Recorder {
+    type Varyings = Coords;
+
+    fn shade(&self, varyings: &Coords, derivatives: &Derivatives<Coords>) -> Option<(u8, u8, u8, u8)> {
+        self.fragments.lock().unwrap().push((*varyings, *derivatives));
+        Some((255, 255, 255, 255))
+    }
+}
+
+#[test]
+fn derivatives_of_linear_varyings() {
+    let mut julia = Julia3D::new(WIDTH, HEIGHT);
+    julia.clear();
+    let recorder = Recorder { fragments: Mutex::new(vec![]) };
+    let vertices = [v3::new(-0.8, -0.7, 0.), v3::new(0.9, -0.3, 0.), v3::new(-0.2, 0.85, 0.)];
+    julia.draw_shaded(&ClipSpace, &recorder, &vertices, &[0_u16, 1, 2], PrimitiveTopology::TriangleList).unwrap();
+
+    let fragments = recorder.fragments.into_inner().unwrap();
+    let pixel = |ndc: v2<f32>| v2::new(
+        ((ndc.x + 1.) / 2. * WIDTH as f32 - 0.5).round() as i32,
+        ((ndc.y + 1.) / 2. * HEIGHT as f32 - 0.5).round() as i32);
+    let shaded: HashSet<_> = fragments.iter().map(|((_, ndc), _)| pixel(*ndc)).collect();
+    assert_eq!(shaded.len(), fragments.len());
+
+    let (dx, dy) = (2. / WIDTH as f32, 2. / HEIGHT as f32);
+    let near = |a: f32, b: f32| (a - b).abs() < 1e-5;
+    let mut with_helpers = 0;
+    for ((_, ndc), derivatives) in fragments.iter() {
+        let (along_x, along_y) = (derivatives.dx, derivatives.dy);
+        assert!(near(along_x.0, dx) && near(along_y.0, 0.), "d(x) = ({}, {}) at {:?}", along_x.0, along_y.0, ndc);
+        assert!(near(along_x.1.x, dx) && near(along_x.1.y, 0.), "d(xy)/dx = {:?} at {:?}", along_x.1, ndc);
+        assert!(near(along_y.1.x, 0.) && near(along_y.1.y, dy), "d(xy)/dy = {:?} at {:?}", along_y.1, ndc);
+
+        // Lanes of the quad outside of the triangle are helpers.
+        let p = pixel(*ndc);
+        let quad = v2::new(p.x & !1, p.y & !1);
+        let lanes = [quad, quad + v2::new(1, 0), quad + v2::new(0, 1), quad + v2::new(1, 1)];
+        if lanes.iter().any(|lane| !shaded.contains(lane)) {
+            with_helpers += 1;
+        }
+    }
+    assert!(with_helpers > 0 && with_helpers < fragments.len());
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use nalgebra as na;
+use na::Matrix4 as mat4;
+use na::Vector2 as v2;
+use na::Vector3 as v3;
+use common::checker;
+use rust_test::rasterizer_2::{IVertex, Julia3D, PrimitiveTopology, RenderError};
+use rust_test::texture::Texture;
+
+fn vertex(x: f32, y: f32) -> IVertex {
+    IVertex { coords: v3::new(x, y, -2.), tex_coords: v2::new(x, y) }
+}
+
+fn triangle() -> Vec<IVertex> {
+    vec![vertex(-1., -1.), vertex(1., -1.), vertex(0., 1.)]
+}
+
+fn texture() -> Texture {
+    checker((0, 100, 200, 255), (240, 100, 200, 255))
+}
+
+/// Renderer with a triangle already drawn, so that the buffers have
+/// something to lose.
+fn renderer() -> Julia3D {
+    let mut julia = Julia3D::new(64, 48);
+    julia.clear();
+    julia.draw(&triangle(), &[0_u16, 1, 2], PrimitiveTopology::TriangleList, &texture(), mat4::identity()).unwrap();
+    julia
+}
+
+/// Checks that the draw of `f` fails with `expected` and draws nothing.
+fn assert_rejected<F: FnOnce(&mut Julia3D) -> Result<(), RenderError>>(expected: RenderError, f: F) {
+    let mut julia = renderer();
+    let color = julia.framebuffer().color().pixels().to_vec();
+    let depth = julia.framebuffer().depth().to_vec();
+    assert!(color.iter().any(|p| p.3 != 0));
+
+    assert_eq!(f(&mut julia), Err(expected));
+    assert!(julia.framebuffer().color().pixels() == &color[..], "{:?} changed the colors", expected);
+    assert!(julia.framebuffer().depth() == &depth[..], "{:?} changed the depths", expected);
+}
+
+#[test]
+fn index_out_of_range() {
+    let expected = RenderError::IndexOutOfRange { index: 3, vertex_count: 3 };
+    assert_rejected(expected, |julia| {
+        julia.draw(&triangle(), &[0_u16, 1, 2, 2, 1, 3], PrimitiveTopology::TriangleList, &texture(), mat4::identity())
+    });
+    assert_rejected(expected, |julia| {
+        julia.draw(&triangle(), &[0_u32, 1, 2, 2, 1, 3], PrimitiveTopology::TriangleList, &texture(), mat4::identity())
+    });
+    assert_rejected(expected, |julia| {
+        julia.render(&triangle(), &[(0_u32, 1, 2), (2, 1, 3)], &texture(), mat4::identity())
+    });
+    let expected = RenderError::IndexOutOfRange { index: 70000, vertex_count: 3 };
+    assert_rejected(expected, |julia| {
+        julia.draw(&triangle(), &[0_u32, 1, 70000], PrimitiveTopology::TriangleList, &texture(), mat4::identity())
+    });
+}
+
+#[test]
+fn partial_triangle() {
+    let topology = PrimitiveTopology::TriangleList;
+    for &count in [1, 2, 4, 5].iter() {
+        let indices = [0_u16, 1, 2, 0, 1];
+        assert_rejected(RenderError::IndexCountMismatch { index_count: count, topology }, |julia| {
+            julia.draw(&triangle(), &indices[..count], topology, &texture(), mat4::identity())
+        });
+    }
+}
+
+#[test]
+fn repeated_vertex() {
+    let expected = RenderError::DegenerateTriangle { triangle: 1 };
+    assert_rejected(expected, |julia| {
+        julia.draw(&triangle(), &[0_u16, 1, 2, 0, 2, 2], PrimitiveTopology::TriangleList, &texture(), mat4::identity())
+    });
+    assert_rejected(expected, |julia| {
+        julia.render(&triangle(), &[(0_u16, 1, 2), (1, 1, 0)], &texture(), mat4::identity())
+    });
+}
+
+/// Strips and fans repeat vertices to stitch their triangles together, and
+/// triangles flattened by the transform are only culled.
+#[test]
+fn degenerate_triangles_drawn_as_nothing() {
+    let mut julia = renderer();
+    let color = julia.framebuffer().color().pixels().to_vec();
+    julia.reset_frame_stats();
+
+    let strip = [0_u16, 1, 1, 2];
+    julia.draw(&triangle(), &strip, PrimitiveTopology::TriangleStrip, &texture(), mat4::identity()).unwrap();
+    let flat = [vertex(-1., -1.), vertex(0., 0.), vertex(1., 1.)];
+    julia.draw(&flat, &[0_u16, 1, 2], PrimitiveTopology::TriangleList, &texture(), mat4::identity()).unwrap();
+    assert!(julia.framebuffer().color().pixels() == &color[..]);
+    assert_eq!(julia.frame_stats().triangles_culled, 3);
+}
+
+#[test]
+fn nan_vertex() {
+    let mut vertices = triangle();
+    vertices[1].coords.y = f32::NAN;
+    assert_rejected(RenderError::NonFiniteVertex { index: 1 }, |julia| {
+        julia.draw(&vertices, &[0_u16, 1, 2], PrimitiveTopology::TriangleList, &texture(), mat4::identity())
+    });
+    assert_rejected(RenderError::NonFiniteVertex { index: 1 }, |julia| {
+        julia.render(&vertices, &[(0_u16, 1, 2)], &texture(), mat4::identity())
+    });
+}
+
+#[test]
+fn nan_matrix() {
+    let mut model = mat4::identity();
+    model[(0, 3)] = f32::NAN;
+    assert_rejected(RenderError::NonFiniteTransform, |julia| {
+        julia.draw(&triangle(), &[0_u16, 1, 2], PrimitiveTopology::TriangleList, &texture(), model)
+    });
+    assert_rejected(RenderError::NonFiniteTransform, |julia| {
+        julia.render(&triangle(), &[(0_u16, 1, 2)], &texture(), model)
+    });
+}
+
+#[test]
+fn zero_point_size() {
+    assert_rejected(RenderError::InvalidPointSize { size: 0. }, |julia| {
+        julia.draw(&triangle(), &[0_u16, 1, 2], PrimitiveTopology::PointList { size: 0. }, &texture(), mat4::identity())
+    });
+}
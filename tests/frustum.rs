@@ -0,0 +1,96 @@
+mod common;
+
+use nalgebra as na;
+use na::Matrix4 as mat4;
+use common::{checker, cube, renderer, CUBE_TRIANGLES, WHITE};
+use rust_test::rasterizer_2::{BoundingVolume, Instance, Julia3D, Mesh, Projection};
+use rust_test::texture::Texture;
+
+const SIZE: i32 = 32;
+
+fn faces() -> Vec<(u16, u16, u16)> {
+    CUBE_TRIANGLES.chunks(3).map(|f| (f[0], f[1], f[2])).collect()
+}
+
+fn texture() -> Texture {
+    checker((255, 128, 0, 255), (0, 64, 255, 255))
+}
+
+/// Cube from `-0.25` to `0.25` around the point.
+fn model(x: f32, y: f32, z: f32) -> mat4<f32> {
+    na::geometry::Translation3::new(x, y, z).to_homogeneous() * mat4::new_scaling(0.25)
+}
+
+/// Centered, straddling the right side, past the right side and in front of
+/// the near plane.
+fn models() -> Vec<mat4<f32>> {
+    vec![model(0., 0., 0.), model(1.1, 0.5, 0.), model(3., 0., 0.), model(0., 0., 5.)]
+}
+
+/// What drawing the visible cubes without culling gives.
+fn reference() -> Julia3D {
+    let vertices = cube();
+    let mut julia = renderer(SIZE, SIZE);
+    for model in &models()[..2] {
+        julia.render(&vertices, &faces(), &texture(), *model).unwrap();
+    }
+    julia
+}
+
+#[test]
+fn meshes_outside_the_frustum_are_skipped() {
+    let vertices = cube();
+    let faces = faces();
+    for bounds in [BoundingVolume::aabb(&vertices), BoundingVolume::sphere(&vertices)].iter() {
+        let mesh = Mesh::new(&vertices, &faces).with_bounds(*bounds);
+        let mut julia = renderer(SIZE, SIZE);
+        for model in models() {
+            julia.render_mesh(&mesh, &texture(), model).unwrap();
+        }
+
+        let counters = julia.cull_counters();
+        assert_eq!((counters.tested, counters.culled, counters.occluded), (4, 2, 0), "{:?}", bounds);
+        assert!(julia.framebuffer().color().pixels() == reference().framebuffer().color().pixels());
+        julia.reset_cull_counters();
+        assert_eq!(julia.cull_counters().tested, 0);
+    }
+}
+
+#[test]
+fn instances_outside_the_frustum_are_skipped() {
+    let vertices = cube();
+    let faces = faces();
+    let texture = texture();
+    let instances: Vec<_> = models().into_iter()
+        .map(|model| Instance { model, tint: WHITE, texture: 0 })
+        .collect();
+    let mut julia = renderer(SIZE, SIZE);
+    julia.render_mesh_instanced(&Mesh::new(&vertices, &faces), &[&texture], mat4::identity(), &instances).unwrap();
+
+    let counters = julia.cull_counters();
+    assert_eq!((counters.tested, counters.culled, counters.occluded), (4, 2, 0));
+    assert!(julia.framebuffer().color().pixels() == reference().framebuffer().color().pixels());
+}
+
+#[test]
+fn culled_meshes_are_not_checked() {
+    let vertices = cube();
+    let faces = [(0_u16, 1, 8)];
+    let mut julia = renderer(SIZE, SIZE);
+    assert!(julia.render_mesh(&Mesh::new(&vertices, &faces), &texture(), model(3., 0., 0.)).is_ok());
+    assert!(julia.render_mesh(&Mesh::new(&vertices, &faces), &texture(), model(0., 0., 0.)).is_err());
+    assert_eq!(julia.cull_counters().culled, 1);
+}
+
+#[test]
+fn bounds_straddling_a_plane_are_inside() {
+    let vertices = cube();
+    let bounds = BoundingVolume::aabb(&vertices);
+    let mvp = |x: f32, z: f32| Projection::orthographic(-1., 1., -1., 1., -1., 1.).matrix() * model(x, 0., z);
+    assert!(!bounds.is_outside(&mvp(0., 0.)));
+    assert!(!bounds.is_outside(&mvp(-1.2, 0.)));
+    assert!(!bounds.is_outside(&mvp(0., 0.9)));
+    assert!(bounds.is_outside(&mvp(-1.3, 0.)));
+    assert!(bounds.is_outside(&mvp(0., -1.3)));
+    assert!(bounds.is_outside(&mvp(0., 1.3)));
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use nalgebra as na;
+use na::Matrix4 as mat4;
+use na::Vector2 as v2;
+use na::Vector3 as v3;
+use common::{checker, draw_rect, Color};
+use rust_test::rasterizer_2::{
+    BlendState, DepthState, IVertex, Julia3D, PrimitiveTopology, RenderError, SamplePattern, StencilOp, StencilState,
+};
+use rust_test::texture::Texture;
+
+fn texture() -> Texture {
+    checker((200, 80, 20, 255), (20, 90, 220, 255))
+}
+
+/// Linear congruential generator, for scenes that are the same every run.
+struct Random(u64);
+
+impl Random {
+    /// Uniform in `[min, max)`.
+    fn next(&mut self, min: f32, max: f32) -> f32 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        min + (self.0 >> 40) as f32 / (1 << 24) as f32 * (max - min)
+    }
+}
+
+fn random_triangles(count: usize) -> Vec<IVertex> {
+    let mut random = Random(7);
+    let mut vertices = vec![];
+    for _ in 0..count {
+        let center = v3::new(random.next(-2.5, 2.5), random.next(-2., 2.), random.next(-8., -2.5));
+        for _ in 0..3 {
+            let offset = v3::new(random.next(-1.5, 1.5), random.next(-1.5, 1.5), random.next(-0.5, 0.5));
+            vertices.push(IVertex {
+                coords      : center + offset,
+                tex_coords  : v2::new(random.next(-1., 1.), random.next(-1., 1.)),
+            });
+        }
+    }
+    vertices
+}
+
+struct Scene {
+    color           : Vec<Color>,
+    depth           : Vec<f32>,
+    samples_passed  : u64,
+    fragments       : u64,
+}
+
+/// Draws random overlapping triangles. A stencil state changing the
+/// stencil of samples failing the depth test keeps the hierarchical depth
+/// buffer from skipping them.
+fn render(samples: usize, stencil_state: StencilState) -> Scene {
+    let mut julia = Julia3D::new(257, 191);
+    julia.set_sample_pattern(SamplePattern::standard(samples).unwrap());
+    julia.set_stencil_state(stencil_state);
+    julia.clear();
+    let vertices = random_triangles(400);
+    let indices: Vec<u32> = (0..vertices.len() as u32).collect();
+    let texture = texture();
+    let samples_passed = julia.occlusion_query(|julia| {
+        for triangle in indices.chunks(3) {
+            julia.draw(&vertices, triangle, PrimitiveTopology::TriangleList, &texture, mat4::identity())?;
+        }
+        Ok(())
+    }).unwrap();
+    julia.resolve();
+    Scene {
+        color           : julia.framebuffer().color().pixels().to_vec(),
+        depth           : julia.framebuffer().depth().to_vec(),
+        samples_passed,
+        fragments       : julia.frame_stats().fragments_generated,
+    }
+}
+
+#[test]
+fn hierarchical_depth_changes_nothing() {
+    let without_hiz = StencilState {
+        enabled         : true,
+        depth_fail_op   : StencilOp::IncrementClamp,
+        ..StencilState::default()
+    };
+    for &samples in [1, 4].iter() {
+        let with = render(samples, StencilState::default());
+        let without = render(samples, without_hiz);
+        assert!(with.fragments < without.fragments, "nothing skipped with {} samples", samples);
+        assert!(with.samples_passed > 0);
+        assert_eq!(with.samples_passed, without.samples_passed, "{} samples", samples);
+        assert!(with.color == without.color, "{} samples", samples);
+        assert!(with.depth == without.depth, "{} samples", samples);
+    }
+}
+
+/// Draws the square of side `2 * size` centered on `(x, y)` at depth `z`.
+fn quad(julia: &mut Julia3D, x: f32, y: f32, size: f32, z: f32) -> Result<(), RenderError> {
+    draw_rect(julia, (x - size, y - size), (x + size, y + size), z, &texture())
+}
+
+#[test]
+fn occlusion_query_of_hidden_and_visible_proxies() {
+    let mut julia = Julia3D::new(128, 96);
+    julia.clear();
+    // Wall over the left half of the view.
+    quad(&mut julia, -2., 0., 2., -2.).unwrap();
+    let color = julia.framebuffer().color().pixels().to_vec();
+    let depth = julia.framebuffer().depth().to_vec();
+
+    julia.set_depth_state(DepthState { write_enabled: false, ..DepthState::default() });
+    julia.set_blend_state(BlendState::keep());
+    let hidden = julia.occlusion_query(|julia| quad(julia, -1., 0., 0.5, -6.)).unwrap();
+    let in_front = julia.occlusion_query(|julia| quad(julia, -1., 0., 0.5, -1.5)).unwrap();
+    let beside = julia.occlusion_query(|julia| quad(julia, 3., 0., 0.5, -6.)).unwrap();
+    assert_eq!(hidden, 0);
+    assert!(in_front > 0);
+    assert!(beside > 0);
+    assert!(julia.framebuffer().color().pixels() == &color[..]);
+    assert!(julia.framebuffer().depth() == &depth[..]);
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use nalgebra as na;
+use na::Matrix4 as mat4;
+use na::Vector3 as v3;
+use common::{checker, rect, WHITE};
+use rust_test::rasterizer_2::{IVertex, Instance, Julia3D, RenderError};
+
+fn quad() -> (Vec<IVertex>, Vec<(u16, u16, u16)>) {
+    (rect((-1., -1.), (1., 1.), 0.), vec![(0, 1, 2), (0, 2, 3)])
+}
+
+fn model(x: f32, y: f32, z: f32, angle: f32) -> mat4<f32> {
+    let translation = na::geometry::Translation3::new(x, y, z).to_homogeneous();
+    let rotation = na::geometry::Rotation3::from_axis_angle(&v3::y_axis(), angle).to_homogeneous();
+    translation * rotation
+}
+
+fn instances() -> Vec<Instance> {
+    vec![
+        Instance { model: model(-0.6, 0.2, -3., 0.3), tint: WHITE, texture: 0 },
+        Instance { model: model(0.4, -0.1, -3.5, -0.5), tint: WHITE, texture: 1 },
+        Instance { model: model(0., 0.5, -2.5, 1.), tint: WHITE, texture: 0 },
+    ]
+}
+
+#[test]
+fn instanced_render_matches_separate_renders() {
+    let (vertices, faces) = quad();
+    let textures = [checker((255, 128, 0, 255), (0, 64, 255, 255)), checker((10, 200, 30, 255), (90, 0, 90, 255))];
+    let instances = instances();
+
+    let mut separate = Julia3D::new(97, 61);
+    separate.clear();
+    for instance in &instances {
+        separate.render(&vertices, &faces, &textures[instance.texture], instance.model).unwrap();
+    }
+
+    let mut instanced = Julia3D::new(97, 61);
+    instanced.clear();
+    instanced.render_instanced(&vertices, &faces, &[&textures[0], &textures[1]], mat4::identity(), &instances).unwrap();
+
+    let drawn = separate.framebuffer().color().pixels().iter().filter(|p| p.3 != 0).count();
+    assert!(drawn > 97 * 61 / 10, "only {} pixels drawn", drawn);
+    assert!(separate.framebuffer().color().pixels() == instanced.framebuffer().color().pixels());
+    // The instanced vertex shader multiplies by the model matrix first, so
+    // depths may differ in the last bits.
+    for (a, b) in separate.framebuffer().depth().iter().zip(instanced.framebuffer().depth()) {
+        assert!((a - b).abs() <= a.abs() * 1e-5, "{} != {}", a, b);
+    }
+}
+
+#[test]
+fn instance_texture_out_of_range() {
+    let (vertices, faces) = quad();
+    let texture = checker(WHITE, (0, 0, 0, 255));
+    let mut instances = instances();
+    instances[1].texture = 1;
+
+    let mut julia = Julia3D::new(97, 61);
+    julia.clear();
+    let result = julia.render_instanced(&vertices, &faces, &[&texture], mat4::identity(), &instances);
+    assert_eq!(result, Err(RenderError::TextureIndexOutOfRange { instance: 1, texture: 1, texture_count: 1 }));
+    assert!(julia.framebuffer().color().pixels().iter().all(|p| *p == (0, 0, 0, 0)));
+}
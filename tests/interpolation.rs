@@ -0,0 +1,72 @@
+use nalgebra as na;
+use na::Vector2 as v2;
+use na::Vector4 as v4;
+use rust_test::julia::{Derivatives, Flat, FragmentShader, NoPerspective, VertexShader};
+use rust_test::rasterizer_2::{Julia3D, PrimitiveTopology};
+use std::sync::Mutex;
+
+const WIDTH: i32 = 61;
+const HEIGHT: i32 = 37;
+
+/// `u` perspective-correctly and linearly in window space, the id of the
+/// vertex, and the normalized device coordinates.
+type Attributes = (f32, NoPerspective<f32>, Flat<u32>, NoPerspective<v2<f32>>);
+
+/// Corner of a quad with `w` growing from 1 on the left to 3 on the right.
+#[derive(Clone, Copy)]
+struct Corner {
+    ndc         : v2<f32>,
+    u           : f32,
+    id          : u32,
+}
+
+struct Slanted;
+
+impl VertexShader for Slanted {
+    type Input = Corner;
+    type Varyings = Attributes;
+
+    fn shade(&self, corner: &Corner) -> (v4<f32>, Attributes) {
+        let w = 1. + 2. * corner.u;
+        let coords = v4::new(corner.ndc.x * w, corner.ndc.y * w, 0., w);
+        (coords, (corner.u, NoPerspective(corner.u), Flat(corner.id), NoPerspective(corner.ndc)))
+    }
+}
+
+/// Keeps the varyings of every fragment.
+struct Recorder {
+    fragments   : Mutex<Vec<Attributes>>,
+}
+
+impl FragmentShader for Recorder {
+    type Varyings = Attributes;
+
+    fn shade(&self, varyings: &Attributes, _: &Derivatives<Attributes>) -> Option<(u8, u8, u8, u8)> {
+        self.fragments.lock().unwrap().push(*varyings);
+        Some((255, 255, 255, 255))
+    }
+}
+
+#[test]
+fn perspective_correct_linear_and_flat_attributes() {
+    let mut julia = Julia3D::new(WIDTH, HEIGHT);
+    julia.clear();
+    let corner = |x: f32, y: f32, id| Corner { ndc: v2::new(x, y), u: (x + 0.9) / 1.8, id };
+    let corners = [corner(-0.9, -0.9, 10), corner(0.9, -0.9, 11), corner(0.9, 0.9, 12), corner(-0.9, 0.9, 13)];
+    let recorder = Recorder { fragments: Mutex::new(vec![]) };
+    julia.draw_shaded(&Slanted, &recorder, &corners, &[0_u16, 1, 2, 2, 3, 0], PrimitiveTopology::TriangleList).unwrap();
+
+    let fragments = recorder.fragments.into_inner().unwrap();
+    assert!(fragments.len() > (WIDTH * HEIGHT) as usize / 2);
+    for &(u, linear, id, ndc) in fragments.iter() {
+        // How far across the quad the fragment is on the screen. `1 / w`
+        // and `u / w` change linearly there, `u` doesn't.
+        let s = (ndc.0.x + 0.9) / 1.8;
+        let w = 1. / (1. - s * 2. / 3.);
+        assert!((u - s * w / 3.).abs() < 1e-4, "u is {} at {}", u, s);
+        assert!((linear.0 - s).abs() < 1e-4, "linear u is {} at {}", linear.0, s);
+        // Below the diagonal, the first triangle.
+        let below = ndc.0.y < ndc.0.x;
+        assert!(id.0 == if below { 10 } else { 12 } || (ndc.0.y - ndc.0.x).abs() < 0.1, "id {} at {:?}", id.0, ndc.0);
+    }
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use nalgebra as na;
+use na::Matrix4 as mat4;
+use na::Vector2 as v2;
+use na::Vector3 as v3;
+use common::{pixel, renderer, solid, BLANK, WHITE};
+use rust_test::rasterizer_2::{IVertex, PrimitiveTopology, SamplePattern};
+
+const SIZE: i32 = 32;
+
+#[test]
+fn standard_patterns() {
+    for &samples in [1, 2, 4, 8].iter() {
+        assert_eq!(SamplePattern::standard(samples).map(|pattern| pattern.samples()), Some(samples));
+    }
+    for &samples in [0, 3, 16].iter() {
+        assert!(SamplePattern::standard(samples).is_none(), "{} samples", samples);
+    }
+}
+
+/// Pixels along the diagonal edge of a triangle get some of their samples
+/// covered, and resolve to a blend of the triangle and the background.
+#[test]
+fn resolve_blends_edge_pixels() {
+    let mut julia = renderer(SIZE, SIZE);
+    julia.set_sample_pattern(SamplePattern::standard(4).unwrap());
+    julia.clear();
+    let vertices: Vec<_> = [(-1., -1.), (1., -1.), (-1., 1.)].iter()
+        .map(|&(x, y)| IVertex { coords: v3::new(x, y, 0.), tex_coords: v2::new(0., 0.) })
+        .collect();
+    julia.draw(&vertices, &[0_u16, 1, 2], PrimitiveTopology::TriangleList, &solid(WHITE), mat4::identity()).unwrap();
+    julia.resolve();
+
+    let quarters: Vec<u8> = (0..=4).map(|k| ((255 * k + 2) / 4) as u8).collect();
+    let mut partial = 0;
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let color = pixel(&julia, x, y);
+            // The hypotenuse runs through the pixels with `x + y = 31`.
+            if x + y < 31 {
+                assert_eq!(color, WHITE, "pixel ({}, {})", x, y);
+            } else if x + y > 31 {
+                assert_eq!(color, BLANK, "pixel ({}, {})", x, y);
+            } else {
+                assert!(quarters.contains(&color.3), "pixel ({}, {}) is {:?}", x, y, color);
+                assert_eq!((color.0, color.1, color.2), (color.3, color.3, color.3));
+                partial += (color != WHITE && color != BLANK) as usize;
+            }
+        }
+    }
+    assert_eq!(partial, SIZE as usize);
+}
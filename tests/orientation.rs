@@ -0,0 +1,61 @@
+mod common;
+
+use common::{draw_rect, renderer, solid, Color};
+use rust_test::rasterizer_2::{CullMode, Framebuffer, Julia3D, Origin, Viewport};
+use rust_test::texture::Texture;
+
+const SIZE: u32 = 16;
+const RED: Color = (255, 0, 0, 255);
+const BLUE: Color = (0, 0, 255, 255);
+
+/// Draws the rectangle from `y0` to `y1` across the whole width.
+fn band(julia: &mut Julia3D, y0: f32, y1: f32, color: Color) {
+    draw_rect(julia, (-1., y0), (1., y1), 0., &solid(color)).unwrap();
+}
+
+#[test]
+fn bitmaps_and_renders_have_the_same_rows() {
+    // Red on top, blue at the bottom.
+    let mut image = bmp::Image::new(SIZE, SIZE);
+    for (x, y) in image.coordinates() {
+        let (r, g, b, _) = if y < SIZE / 2 { RED } else { BLUE };
+        image.set_pixel(x, y, bmp::Pixel::new(r, g, b));
+    }
+    let path = std::env::temp_dir().join("julia_orientation.bmp");
+    image.save(&path).unwrap();
+    let loaded = Texture::new(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let mut julia = renderer(SIZE as i32, SIZE as i32);
+    let mut framebuffer = Framebuffer::new(SIZE as i32, SIZE as i32);
+    julia.render_to(&mut framebuffer, |julia| {
+        julia.clear();
+        band(julia, 0., 1., RED);
+        band(julia, -1., 0., BLUE);
+    });
+
+    assert_eq!(loaded.pixels()[0], BLUE);
+    assert!(framebuffer.color().pixels() == loaded.pixels());
+}
+
+/// A top-left origin puts `y = 1` on the first row, and keeps the faces
+/// counter-clockwise on the screen facing the camera.
+#[test]
+fn top_left_origin_renders_upside_down() {
+    let mut julia = renderer(SIZE as i32, SIZE as i32 * 2);
+    julia.set_viewport(Viewport::new(0, 0, SIZE as i32, SIZE as i32 * 2).with_origin(Origin::TopLeft));
+    julia.set_cull_mode(CullMode::Back);
+    band(&mut julia, 0.5, 1., RED);
+    band(&mut julia, -1., -0.5, BLUE);
+
+    let pixels = julia.framebuffer().color().pixels();
+    let row = |y: u32| &pixels[(y * SIZE) as usize..((y + 1) * SIZE) as usize];
+    for y in 0..SIZE * 2 {
+        let expected = match y / (SIZE / 2) {
+            0       => RED,
+            3       => BLUE,
+            _       => (0, 0, 0, 0),
+        };
+        assert!(row(y).iter().all(|p| *p == expected), "row {} isn't {:?}", y, expected);
+    }
+}
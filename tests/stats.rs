@@ -0,0 +1,47 @@
+mod common;
+
+use std::time::Duration;
+use common::{draw_rect, renderer, solid, WHITE};
+use rust_test::rasterizer_2::{FrameStats, Julia3D};
+
+const SIZE: i32 = 32;
+
+/// Draws the left half of the window, then the left quarter behind it.
+fn draw(julia: &mut Julia3D) -> FrameStats {
+    julia.clear();
+    julia.reset_frame_stats();
+    draw_rect(julia, (-1., -1.), (0., 1.), 0.5, &solid(WHITE)).unwrap();
+    draw_rect(julia, (-1., -1.), (-0.5, 1.), 0., &solid(WHITE)).unwrap();
+    julia.frame_stats()
+}
+
+fn times(stats: &FrameStats) -> [Duration; 5] {
+    [stats.vertex, stats.setup, stats.raster, stats.fragment, stats.depth_test]
+}
+
+#[test]
+fn counts_without_profiling() {
+    let mut julia = renderer(SIZE, SIZE);
+    let stats = draw(&mut julia);
+    assert!(times(&stats).iter().all(|t| *t == Duration::default()), "{:?}", stats);
+    assert_eq!(stats.triangles_submitted, 4);
+    assert_eq!(stats.triangles_culled, 0);
+    // The hierarchical depth buffer skips the hidden quarter.
+    assert_eq!(stats.fragments_generated, (SIZE * SIZE / 2) as u64);
+    assert_eq!(stats.fragments_passed, (SIZE * SIZE / 2) as u64);
+    assert_eq!(stats.pixels, (SIZE * SIZE) as u64);
+    assert_eq!(stats.overdraw(), 0.5);
+}
+
+#[test]
+fn times_while_profiling() {
+    let mut julia = renderer(SIZE, SIZE);
+    julia.set_profiling(true);
+    let stats = draw(&mut julia);
+    assert!(times(&stats).iter().all(|t| *t > Duration::default()), "{:?}", stats);
+
+    julia.set_profiling(false);
+    let unprofiled = draw(&mut julia);
+    assert!(times(&unprofiled).iter().all(|t| *t == Duration::default()));
+    assert_eq!(unprofiled.fragments_passed, stats.fragments_passed);
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{draw_rect, renderer, solid, WHITE};
+use rust_test::rasterizer_2::{CompareFunc, Julia3D, StencilOp, StencilState};
+
+const SIZE: i32 = 16;
+
+/// Draws the rectangle from `x0` to `x1` across the whole height at depth `z`.
+fn rect(julia: &mut Julia3D, x0: f32, x1: f32, z: f32) {
+    draw_rect(julia, (x0, -1.), (x1, 1.), z, &solid(WHITE)).unwrap();
+}
+
+fn stencil_at(julia: &Julia3D, x: i32) -> Vec<u8> {
+    julia.framebuffer().stencil().iter()
+        .enumerate()
+        .filter(|(i, _)| *i as i32 % SIZE == x)
+        .map(|(_, s)| *s)
+        .collect()
+}
+
+#[test]
+fn depth_fail_op_only_on_depth_failure() {
+    let mut julia = renderer(SIZE, SIZE);
+    rect(&mut julia, -1., 1., 0.);
+    julia.set_stencil_state(StencilState {
+        enabled         : true,
+        reference       : 7,
+        depth_fail_op   : StencilOp::Replace,
+        pass_op         : StencilOp::IncrementClamp,
+        ..StencilState::default()
+    });
+    // In front on the left, behind on the right.
+    rect(&mut julia, -1., 0., 0.5);
+    rect(&mut julia, 0., 1., -0.5);
+    for x in 0..SIZE {
+        let expected = if x < SIZE / 2 { 1 } else { 7 };
+        assert!(stencil_at(&julia, x).iter().all(|&s| s == expected), "column {}", x);
+    }
+
+    // Failing the stencil test skips the depth test and its op.
+    let stencil = julia.framebuffer().stencil().to_vec();
+    julia.set_stencil_state(StencilState {
+        enabled         : true,
+        compare         : CompareFunc::Never,
+        reference       : 3,
+        depth_fail_op   : StencilOp::Replace,
+        pass_op         : StencilOp::Replace,
+        ..StencilState::default()
+    });
+    rect(&mut julia, -1., 1., -0.5);
+    assert!(julia.framebuffer().stencil() == &stencil[..]);
+}
+
+#[test]
+fn write_mask_keeps_masked_bits() {
+    let table = [
+        (StencilOp::Invert,     0x0f,   0b1010_0101),
+        (StencilOp::Replace,    0xf0,   0b1111_1010),
+        (StencilOp::Zero,       0x3c,   0b1000_0010),
+        (StencilOp::Replace,    0x00,   0b1010_1010),
+    ];
+    for &(op, write_mask, expected) in table.iter() {
+        let mut julia = renderer(SIZE, SIZE);
+        julia.set_stencil_state(StencilState {
+            enabled         : true,
+            reference       : 0xff,
+            write_mask,
+            pass_op         : op,
+            clear_stencil   : 0b1010_1010,
+            ..StencilState::default()
+        });
+        julia.clear();
+        rect(&mut julia, -1., 1., 0.);
+        assert!(julia.framebuffer().stencil().iter().all(|&s| s == expected),
+            "{:?} with mask {:#x}", op, write_mask);
+    }
+}
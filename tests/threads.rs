@@ -0,0 +1,66 @@
+mod common;
+
+use nalgebra as na;
+use common::{checker, cube, Color, CUBE_EDGES, CUBE_TRIANGLES};
+use rust_test::rasterizer_2::{
+    BlendFactor, BlendState, Julia3D, PrimitiveTopology, SamplePattern, StencilOp, StencilState,
+};
+
+struct Buffers {
+    color           : Vec<Color>,
+    depth           : Vec<f32>,
+    stencil         : Vec<u8>,
+}
+
+/// Color, depth and stencil of a scene of overlapping cubes and their edges,
+/// blended additively into a multisampled framebuffer.
+fn render(threads: usize) -> Buffers {
+    let mut julia = Julia3D::new(301, 203);
+    julia.set_threads(threads);
+    julia.set_sample_pattern(SamplePattern::standard(4).unwrap());
+    julia.set_blend_state(BlendState {
+        enabled         : true,
+        src_color       : BlendFactor::One,
+        dst_color       : BlendFactor::One,
+        dst_alpha       : BlendFactor::One,
+        ..BlendState::default()
+    });
+    julia.set_stencil_state(StencilState {
+        enabled         : true,
+        depth_fail_op   : StencilOp::DecrementWrap,
+        pass_op         : StencilOp::IncrementWrap,
+        ..StencilState::default()
+    });
+    julia.clear();
+
+    let (vertices, texture) = (cube(), checker((40, 20, 10, 60), (10, 30, 50, 90)));
+    for k in 0..12 {
+        let t = k as f32 * 0.7;
+        let translation = na::geometry::Translation3::new(t.sin() * 2.5, t.cos() * 1.5, -3.5 - k as f32 * 0.2).to_homogeneous();
+        let rotation = na::geometry::Rotation3::from_euler_angles(t, t * 0.5, 0.3).to_homogeneous();
+        let model = translation * rotation;
+        julia.draw(&vertices, &CUBE_TRIANGLES, PrimitiveTopology::TriangleList, &texture, model).unwrap();
+        julia.draw(&vertices, &CUBE_EDGES, PrimitiveTopology::LineList, &texture, model).unwrap();
+    }
+    julia.resolve();
+
+    let framebuffer = julia.framebuffer();
+    Buffers {
+        color           : framebuffer.color().pixels().to_vec(),
+        depth           : framebuffer.depth().to_vec(),
+        stencil         : framebuffer.stencil().to_vec(),
+    }
+}
+
+#[test]
+fn threads_draw_the_same() {
+    let single = render(1);
+    let drawn = single.color.iter().filter(|p| p.3 != 0).count();
+    assert!(drawn > 301 * 203 / 4, "only {} pixels drawn", drawn);
+    assert!(single.stencil.iter().any(|&s| s > 1));
+
+    let threaded = render(8);
+    assert!(single.color == threaded.color);
+    assert!(single.depth == threaded.depth);
+    assert!(single.stencil == threaded.stencil);
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use nalgebra as na;
+use na::Matrix4 as mat4;
+use na::Vector2 as v2;
+use na::Vector3 as v3;
+use na::Vector4 as v4;
+use common::{renderer, solid, Color, WHITE};
+use rust_test::julia::{Derivatives, Flat, FragmentShader, VertexShader};
+use rust_test::rasterizer_2::{CullMode, IVertex, Julia3D, PrimitiveTopology};
+
+const SIZE: i32 = 32;
+
+/// Draws white primitives between the centers of the given pixels.
+fn draw_white(julia: &mut Julia3D, pixels: &[(i32, i32)], indices: &[u16], topology: PrimitiveTopology) {
+    let ndc = |p: i32| (p as f32 + 0.5) / SIZE as f32 * 2. - 1.;
+    let vertices: Vec<_> = pixels.iter()
+        .map(|&(x, y)| IVertex { coords: v3::new(ndc(x), ndc(y), 0.), tex_coords: v2::new(0., 0.) })
+        .collect();
+    julia.draw(&vertices, indices, topology, &solid(WHITE), mat4::identity()).unwrap();
+}
+
+/// The white pixels, row by row.
+fn white(julia: &Julia3D) -> Vec<(i32, i32)> {
+    julia.framebuffer().color().pixels().iter().enumerate()
+        .filter(|(_, p)| **p == WHITE)
+        .map(|(i, _)| (i as i32 % SIZE, i as i32 / SIZE))
+        .collect()
+}
+
+#[test]
+fn points_are_squares_around_their_pixel() {
+    let mut julia = renderer(SIZE, SIZE);
+    draw_white(&mut julia, &[(10, 20), (3, 4)], &[0, 1], PrimitiveTopology::PointList { size: 3. });
+    let mut expected = vec![];
+    for &(x, y) in [(3, 4), (10, 20)].iter() {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                expected.push((x + dx, y + dy));
+            }
+        }
+    }
+    expected.sort_by_key(|&(x, y)| (y, x));
+    assert_eq!(white(&julia), expected);
+}
+
+#[test]
+fn line_lists_and_strips() {
+    let corners = [(2, 5), (20, 5), (20, 25)];
+    let row: Vec<_> = (2..=20).map(|x| (x, 5)).collect();
+
+    let mut julia = renderer(SIZE, SIZE);
+    draw_white(&mut julia, &corners, &[0, 1], PrimitiveTopology::LineList);
+    assert_eq!(white(&julia), row);
+
+    let mut julia = renderer(SIZE, SIZE);
+    draw_white(&mut julia, &corners, &[0, 1, 2], PrimitiveTopology::LineStrip);
+    let mut expected = row.clone();
+    expected.extend((6..=25).map(|y| (20, y)));
+    assert_eq!(white(&julia), expected);
+}
+
+#[test]
+fn triangle_strips_keep_their_winding() {
+    let mut julia = renderer(SIZE, SIZE);
+    julia.set_cull_mode(CullMode::Back);
+    // Zigzag from the top to the bottom edge, three quads across the view.
+    let corners: Vec<_> = (0..8).map(|k| (k / 2 * 11 - 1, if k % 2 == 0 { SIZE } else { -1 })).collect();
+    let indices: Vec<u16> = (0..8).collect();
+    draw_white(&mut julia, &corners, &indices, PrimitiveTopology::TriangleStrip);
+    assert_eq!(white(&julia).len(), (SIZE * SIZE) as usize);
+}
+
+/// Vertices are given in clip space along with an id, flat across the
+/// primitives.
+struct Ids;
+
+impl VertexShader for Ids {
+    type Input = (v3<f32>, u32);
+    type Varyings = Flat<u32>;
+
+    fn shade(&self, (position, id): &(v3<f32>, u32)) -> (v4<f32>, Flat<u32>) {
+        (v4::new(position.x, position.y, position.z, 1.), Flat(*id))
+    }
+}
+
+/// Writes the id of the provoking vertex into the red channel.
+struct ShowId;
+
+impl FragmentShader for ShowId {
+    type Varyings = Flat<u32>;
+
+    fn shade(&self, id: &Flat<u32>, _: &Derivatives<Flat<u32>>) -> Option<Color> {
+        Some((id.0 as u8, 0, 0, 255))
+    }
+}
+
+fn draw(julia: &mut Julia3D, corners: &[(f32, f32)], indices: &[u16], topology: PrimitiveTopology) {
+    let inputs: Vec<_> = corners.iter().enumerate()
+        .map(|(id, &(x, y))| (v3::new(x, y, 0.), id as u32))
+        .collect();
+    julia.draw_shaded(&Ids, &ShowId, &inputs, indices, topology).unwrap();
+}
+
+fn id(julia: &Julia3D, x: i32, y: i32) -> u8 {
+    common::pixel(julia, x, y).0
+}
+
+#[test]
+fn first_vertex_of_strip_triangles_provokes() {
+    let mut julia = renderer(SIZE, SIZE);
+    let corners = [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)];
+    draw(&mut julia, &corners, &[0, 1, 2, 3], PrimitiveTopology::TriangleStrip);
+    // Below and above the diagonal from the top-left to the bottom-right.
+    assert_eq!(id(&julia, 4, 4), 0);
+    assert_eq!(id(&julia, 27, 27), 1);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use nalgebra as na;
+use na::Matrix4 as mat4;
+use na::Vector2 as v2;
+use na::Vector3 as v3;
+use common::{solid, Color, BLANK};
+use rust_test::rasterizer_2::{
+    BlendFactor, BlendState, CompareFunc, DepthState, IVertex, Julia3D, PrimitiveTopology,
+};
+
+const COLOR: Color = (12, 34, 56, 78);
+
+/// Renderer adding up the color of every fragment, whatever its depth, with
+/// window coordinates mapping straight onto `[-1, 1]`.
+fn renderer(width: i32, height: i32) -> Julia3D {
+    let mut julia = common::renderer(width, height);
+    julia.set_depth_state(DepthState { compare: CompareFunc::Always, ..DepthState::default() });
+    julia.set_blend_state(BlendState {
+        enabled         : true,
+        src_color       : BlendFactor::One,
+        dst_color       : BlendFactor::One,
+        dst_alpha       : BlendFactor::One,
+        ..BlendState::default()
+    });
+    julia
+}
+
+fn draw(julia: &mut Julia3D, points: &[v2<f32>], indices: &[u32], topology: PrimitiveTopology) {
+    let vertices: Vec<_> = points.iter()
+        .map(|p| IVertex { coords: v3::new(p.x, p.y, 0.), tex_coords: v2::new(0., 0.) })
+        .collect();
+    julia.draw(&vertices, indices, topology, &solid(COLOR), mat4::identity()).unwrap();
+}
+
+/// Window coordinates of a point in `[-1, 1]`.
+fn window(julia: &Julia3D, p: v2<f32>) -> v2<f32> {
+    let shape = julia.framebuffer().shape().map(|c| c as f32);
+    (p + v2::new(1., 1.)).component_mul(&shape) / 2.
+}
+
+fn cross(a: v2<f32>, b: v2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn full_viewport_quad(width: i32, height: i32) {
+    let mut julia = renderer(width, height);
+    let corners = [v2::new(-1., -1.), v2::new(1., -1.), v2::new(1., 1.), v2::new(-1., 1.)];
+    draw(&mut julia, &corners, &[0, 1, 2, 0, 2, 3], PrimitiveTopology::TriangleList);
+    for (i, pixel) in julia.framebuffer().color().pixels().iter().enumerate() {
+        assert_eq!(*pixel, COLOR, "pixel {} of {}x{}", i, width, height);
+    }
+}
+
+/// Draws a fan around an off-center point, its outer vertices at uneven
+/// angles and distances, and checks that the pixels well inside got exactly
+/// one fragment and the others at most one.
+fn fan(width: i32, height: i32) {
+    let mut julia = renderer(width, height);
+    let center_point = v2::new(0.0371, -0.0213);
+    let mut points = vec![center_point];
+    let spokes = 41;
+    for k in 0..spokes {
+        let angle = k as f32 / spokes as f32 * std::f32::consts::PI * 2. + (k % 3) as f32 * 0.013;
+        let radius = 0.8 + (k % 5) as f32 * 0.031;
+        points.push(center_point + v2::new(angle.cos(), angle.sin()) * radius);
+    }
+    points.push(points[1]);
+    let indices: Vec<u32> = (0..points.len() as u32).collect();
+    draw(&mut julia, &points, &indices, PrimitiveTopology::TriangleFan);
+
+    // Pixel centers closer to an outer edge than this may go either way.
+    let margin = 1e-3;
+    let points: Vec<_> = points.iter().map(|p| window(&julia, *p)).collect();
+    let c = points[0];
+    let angle = |p: v2<f32>| {
+        let angle = (p.y - c.y).atan2(p.x - c.x);
+        if angle < 0. { angle + std::f32::consts::PI * 2. } else { angle }
+    };
+    let angles: Vec<_> = points[1..spokes + 1].iter().map(|p| angle(*p)).collect();
+    let mut inside = 0;
+    for (i, pixel) in julia.framebuffer().color().pixels().iter().enumerate() {
+        let p = v2::new((i % width as usize) as f32 + 0.5, (i / width as usize) as f32 + 0.5);
+        // Outer edge of the triangle of the fan the center is in.
+        let k = angles.iter().take_while(|a| **a <= angle(p)).count();
+        let (a, b) = (points[k.max(1)], points[k.max(1) + 1]);
+        let well_inside = k > 0 && cross(b - a, p - a) / (b - a).norm() > margin;
+        if well_inside {
+            assert_eq!(*pixel, COLOR, "pixel {} of {}x{} inside the fan", i, width, height);
+            inside += 1;
+        } else {
+            assert!(*pixel == COLOR || *pixel == BLANK, "pixel {} of {}x{} is {:?}", i, width, height, pixel);
+        }
+    }
+    assert!(inside > (width * height) as usize / 3);
+}
+
+#[test]
+fn full_viewport_quad_is_watertight() {
+    full_viewport_quad(97, 61);
+    full_viewport_quad(1920, 1080);
+}
+
+#[test]
+fn triangle_fan_is_watertight() {
+    fan(97, 61);
+    fan(1920, 1080);
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use nalgebra as na;
+use na::Matrix4 as mat4;
+use na::Vector2 as v2;
+use na::Vector3 as v3;
+use common::{draw_rect, pixel, renderer, solid, BLANK, WHITE};
+use rust_test::rasterizer_2::{IVertex, Julia3D, PolygonMode, PrimitiveTopology};
+
+const SIZE: i32 = 32;
+
+fn wireframe(polygon_mode: PolygonMode) -> Julia3D {
+    let mut julia = renderer(SIZE, SIZE);
+    julia.set_polygon_mode(polygon_mode);
+    julia
+}
+
+/// Whether a pixel of the column `x`, or of the one before, is drawn in the
+/// row `y`. Edges on pixel boundaries may go either way.
+fn drawn_near(julia: &Julia3D, x: i32, y: i32) -> bool {
+    pixel(julia, x, y) == WHITE || pixel(julia, x - 1, y) == WHITE
+}
+
+#[test]
+fn hidden_line_draws_every_edge_of_screen_aligned_faces() {
+    let mut julia = wireframe(PolygonMode::HiddenLine);
+    draw_rect(&mut julia, (-0.5, -0.5), (0.5, 0.5), 0., &solid(WHITE)).unwrap();
+    // The quad covers pixels 8 to 23.
+    assert!(drawn_near(&julia, 8, 16), "left edge");
+    assert!(drawn_near(&julia, 24, 16), "right edge");
+    let column = |y| (pixel(&julia, 16, y) == WHITE) || (pixel(&julia, 16, y - 1) == WHITE);
+    assert!(column(8), "bottom edge");
+    assert!(column(24), "top edge");
+    // The surface stays blank.
+    assert_eq!(pixel(&julia, 12, 20), BLANK);
+}
+
+#[test]
+fn clipped_edges_are_not_drawn() {
+    let mut julia = wireframe(PolygonMode::Line);
+    let vertices: Vec<_> = [(-1.5, -0.5), (0.5, -0.5), (-0.5, 0.5)].iter()
+        .map(|&(x, y)| IVertex { coords: v3::new(x, y, 0.), tex_coords: v2::new(0., 0.) })
+        .collect();
+    julia.draw(&vertices, &[0_u16, 1, 2], PrimitiveTopology::TriangleList, &solid(WHITE), mat4::identity()).unwrap();
+    assert!(julia.framebuffer().color().pixels().contains(&WHITE));
+    // The window cuts the triangle from row 8 to row 16, where its bottom
+    // and left edges cross the first column.
+    for y in 10..15 {
+        assert_eq!(pixel(&julia, 0, y), BLANK, "pixel (0, {}) of the clipped edge", y);
+    }
+}